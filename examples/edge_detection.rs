@@ -13,6 +13,7 @@ enum Kernel {
     SobelY,
     LaplacianCross,
     LaplacianFull,
+    Canny,
 }
 
 impl Display for Kernel {
@@ -22,6 +23,7 @@ impl Display for Kernel {
             Self::SobelY => write!(f, "sobel_y"),
             Self::LaplacianCross => write!(f, "laplacian_cross"),
             Self::LaplacianFull => write!(f, "laplacian_full"),
+            Self::Canny => write!(f, "canny"),
         }
     }
 }
@@ -41,17 +43,19 @@ fn main() {
         .expect("Unable to decode image")
         .into_luma8()
         .into();
-    let img = img.map_subpixels(|sp| sp as i32);
 
-    let kernel: StaticMatrix<i32, 9> = match args.kernel {
-        Kernel::SobelX => kernel::sobel::x(),
-        Kernel::SobelY => kernel::sobel::y(),
-        Kernel::LaplacianCross => kernel::laplacian::cross(),
-        Kernel::LaplacianFull => kernel::laplacian::full(),
-    };
+    let img = img.map_subpixels(|sp| sp as i32);
 
     let cv_start = Instant::now();
-    let convolution = convolve2d(&img, &kernel);
+    let convolution = match args.kernel {
+        Kernel::SobelX => convolve2d(&img, &kernel::sobel::x::<i32>()),
+        Kernel::SobelY => convolve2d(&img, &kernel::sobel::y::<i32>()),
+        Kernel::LaplacianCross => convolve2d(&img, &kernel::laplacian::cross::<i32>()),
+        Kernel::LaplacianFull => convolve2d(&img, &kernel::laplacian::full::<i32>()),
+        Kernel::Canny => {
+            canny(&img, 1.4, 100.0, 250.0).map(|edge| SubPixels([if edge { 255 } else { 0 }]))
+        }
+    };
     let cv_stop = Instant::now();
 
     let convolution = convolution.map_subpixels(|x| x.abs() as u8);
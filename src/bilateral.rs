@@ -0,0 +1,358 @@
+//! Edge preserving smoothing with the bilateral filter.
+
+use crate::{kernel, Channels, DynamicMatrix, Matrix, MaybeSend, MaybeSync};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// The number of empty cells added to each side of a bilateral grid, so that blurring the grid
+/// does not need to handle its edges.
+const GRID_PADDING: usize = 2;
+
+/// Smooth the specified image while preserving its edges, using a bilateral filter.
+///
+/// Each output pixel is a weighted average of the pixels in a `size` by `size` window around it.
+/// As with a Gaussian blur, the weights fall off with distance, using the weights of
+/// [`kernel::gaussian`] with a standard deviation of `spatial_sigma`. However, they also fall off
+/// as pixels become more different from the one being filtered, following a Gaussian curve with a
+/// standard deviation of `range_sigma`. Pixels on the other side of an edge are very different,
+/// and so are barely averaged in, leaving the edge sharp.
+///
+/// The difference between two pixels is the Euclidean distance between their [`Channels`]. Parts
+/// of the window which fall outside of the image are ignored.
+///
+/// The cost of this filter grows with the area of the window, so for large values of
+/// `spatial_sigma`, [`bilateral_grid`] is much faster.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{bilateral_filter, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(4, 1, vec![0.0, 0.1, 0.9, 1.0]).unwrap();
+///
+/// let output = bilateral_filter(&image, 3, 1.0, 0.2);
+/// assert!(output.get_data()[1] < 0.1);
+/// assert!(output.get_data()[2] > 0.9);
+/// ```
+///
+/// # Panics
+/// If `size` is zero, or if either `spatial_sigma` or `range_sigma` is not positive.
+pub fn bilateral_filter<T>(
+    image: &impl Matrix<T>,
+    size: usize,
+    spatial_sigma: f64,
+    range_sigma: f64,
+) -> DynamicMatrix<T>
+where
+    T: Channels + MaybeSend + MaybeSync,
+{
+    assert!(size > 0, "Window must not be empty");
+    assert!(
+        spatial_sigma > 0.0 && range_sigma > 0.0,
+        "Standard deviations must be positive"
+    );
+    let spatial = kernel::gaussian(size, spatial_sigma);
+    let half = (size >> 1) as isize;
+    let range_coefficient = -0.5 / (range_sigma * range_sigma);
+    let (width, height) = (image.get_width(), image.get_height());
+    let data = image.get_data();
+
+    let filter_pixel = |i: usize| {
+        let (row, col) = ((i / width) as isize, (i % width) as isize);
+        let center = &data[i];
+        let mut sums = vec![0.0; T::CHANNELS];
+        let mut total = 0.0;
+
+        for (j, spatial_weight) in spatial.get_data().iter().enumerate() {
+            let r = row + (j / size) as isize - half;
+            let c = col + (j % size) as isize - half;
+            if r < 0 || r >= height as isize || c < 0 || c >= width as isize {
+                continue;
+            }
+            let pixel = &data[r as usize * width + c as usize];
+
+            let distance: f64 = (0..T::CHANNELS)
+                .map(|k| (pixel.channel(k) - center.channel(k)).powi(2))
+                .sum();
+            let weight = spatial_weight * f64::exp(distance * range_coefficient);
+            total += weight;
+            for (k, sum) in sums.iter_mut().enumerate() {
+                *sum += weight * pixel.channel(k);
+            }
+        }
+        T::from_channels(|k| sums[k] / total)
+    };
+
+    #[cfg(not(feature = "rayon"))]
+    let pixels = 0..data.len();
+    #[cfg(feature = "rayon")]
+    let pixels = (0..data.len()).into_par_iter();
+
+    DynamicMatrix::new(width, height, pixels.map(filter_pixel).collect()).unwrap()
+}
+
+/// Approximate a [`bilateral_filter`] using a bilateral grid, which takes the same time for any
+/// `spatial_sigma`.
+///
+/// The image is placed into a coarse three dimensional grid, indexed by position and by value,
+/// with cells `spatial_sigma` pixels wide and `range_sigma` tall. Blurring this grid and reading
+/// the result back out at each pixel's position and value gives a close approximation of the
+/// bilateral filter, in time proportional to the number of pixels plus the size of the grid. This
+/// is the approach of Paris and Durand.
+///
+/// Unlike [`bilateral_filter`], each channel of a multi-channel image is filtered independently.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{bilateral_grid, DynamicMatrix, Matrix};
+/// let data = (0..64 * 64).map(|i| if i % 64 < 32 { 0.0 } else { 1.0 }).collect();
+/// let image = DynamicMatrix::new(64, 64, data).unwrap();
+///
+/// let output = bilateral_grid(&image, 8.0, 0.1);
+/// assert!(output.get_data()[64 * 32 + 31] < 0.05);
+/// assert!(output.get_data()[64 * 32 + 32] > 0.95);
+/// ```
+///
+/// # Panics
+/// If either `spatial_sigma` or `range_sigma` is not positive.
+pub fn bilateral_grid<T>(
+    image: &impl Matrix<T>,
+    spatial_sigma: f64,
+    range_sigma: f64,
+) -> DynamicMatrix<T>
+where
+    T: Channels + MaybeSend + MaybeSync,
+{
+    assert!(
+        spatial_sigma > 0.0 && range_sigma > 0.0,
+        "Standard deviations must be positive"
+    );
+    let (width, height) = (image.get_width(), image.get_height());
+    let channels: Vec<Vec<f64>> = (0..T::CHANNELS)
+        .map(|k| {
+            let values: Vec<f64> = image.get_data().iter().map(|x| x.channel(k)).collect();
+            let grid = Grid::new(&values, width, height, spatial_sigma, range_sigma);
+            grid.slice(&values, width)
+        })
+        .collect();
+
+    let data = (0..width * height)
+        .map(|i| T::from_channels(|k| channels[k][i]))
+        .collect();
+    DynamicMatrix::new(width, height, data).unwrap()
+}
+
+/// A bilateral grid of a single channel, holding the sum of the values and the number of values
+/// which fall into each cell.
+struct Grid {
+    /// The `(sum, count)` of each cell, indexed by value, then row, then column
+    cells: Vec<(f64, f64)>,
+    /// The number of cells along the `(column, row, value)` axes
+    size: (usize, usize, usize),
+    /// The smallest value in the image
+    min: f64,
+    /// The width of a cell in pixels, and its height in values
+    scale: (f64, f64),
+}
+
+impl Grid {
+    /// Place each value of an image into a grid, and blur it.
+    fn new(values: &[f64], width: usize, height: usize, spatial: f64, range: f64) -> Self {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let cells_along =
+            |extent: f64, scale: f64| (extent / scale) as usize + 1 + 2 * GRID_PADDING;
+        let size = (
+            cells_along(width.saturating_sub(1) as f64, spatial),
+            cells_along(height.saturating_sub(1) as f64, spatial),
+            cells_along((max - min).max(0.0), range),
+        );
+
+        let mut grid = Self {
+            cells: vec![(0.0, 0.0); size.0 * size.1 * size.2],
+            size,
+            min,
+            scale: (spatial, range),
+        };
+        for (i, &value) in values.iter().enumerate() {
+            let (x, y, z) = grid.position(i % width.max(1), i / width.max(1), value);
+            let index = grid.index(x.round() as usize, y.round() as usize, z.round() as usize);
+            grid.cells[index].0 += value;
+            grid.cells[index].1 += 1.0;
+        }
+
+        grid.blur(1);
+        grid.blur(size.0);
+        grid.blur(size.0 * size.1);
+        grid
+    }
+
+    /// Find the position in the grid of a pixel with the specified value.
+    fn position(&self, col: usize, row: usize, value: f64) -> (f64, f64, f64) {
+        let padding = GRID_PADDING as f64;
+        (
+            col as f64 / self.scale.0 + padding,
+            row as f64 / self.scale.0 + padding,
+            (value - self.min) / self.scale.1 + padding,
+        )
+    }
+
+    /// Get the index of the cell at the specified position
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.size.1 + y) * self.size.0 + x
+    }
+
+    /// Blur the grid along the axis whose cells are `stride` apart, using the binomial weights
+    /// `[1, 4, 6, 4, 1] / 16`, which have a standard deviation of one cell.
+    fn blur(&mut self, stride: usize) {
+        const WEIGHTS: [f64; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+        let len = self.cells.len();
+        let original = self.cells.clone();
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            *cell = (0.0, 0.0);
+            for (j, weight) in WEIGHTS.iter().enumerate() {
+                // Cells past the edge of the grid are empty, and the padding keeps every
+                // non-empty cell away from the edges of other rows
+                let offset = (j as isize - 2) * stride as isize;
+                let source = i as isize + offset;
+                if source >= 0 && (source as usize) < len {
+                    let (sum, count) = original[source as usize];
+                    cell.0 += weight * sum;
+                    cell.1 += weight * count;
+                }
+            }
+        }
+    }
+
+    /// Read the filtered value of each pixel out of the grid, using trilinear interpolation.
+    fn slice(&self, values: &[f64], width: usize) -> Vec<f64> {
+        let slice_pixel = |i: usize| {
+            let (x, y, z) = self.position(i % width, i / width, values[i]);
+            let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+            let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+            let (x0, y0, z0) = (x0 as usize, y0 as usize, z0 as usize);
+
+            let (mut sum, mut count) = (0.0, 0.0);
+            for (dz, wz) in [(0, 1.0 - fz), (1, fz)] {
+                for (dy, wy) in [(0, 1.0 - fy), (1, fy)] {
+                    for (dx, wx) in [(0, 1.0 - fx), (1, fx)] {
+                        let (s, c) = self.cells[self.index(x0 + dx, y0 + dy, z0 + dz)];
+                        sum += wx * wy * wz * s;
+                        count += wx * wy * wz * c;
+                    }
+                }
+            }
+            sum / count
+        };
+
+        #[cfg(not(feature = "rayon"))]
+        let pixels = 0..values.len();
+        #[cfg(feature = "rayon")]
+        let pixels = (0..values.len()).into_par_iter();
+
+        pixels.map(slice_pixel).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bilateral_filter, bilateral_grid};
+    use crate::{convolve2d, kernel, DynamicMatrix, Matrix, SubPixels};
+    use std::prelude::v1::*;
+    use test_case::test_case;
+
+    fn step(width: usize, height: usize) -> DynamicMatrix<f64> {
+        let data = (0..width * height)
+            .map(|i| if i % width < width / 2 { 0.2 } else { 0.8 })
+            .collect();
+        DynamicMatrix::new(width, height, data).unwrap()
+    }
+
+    #[test]
+    fn constant_image_is_unchanged() {
+        let image = DynamicMatrix::new(5, 4, vec![0.25f32; 20]).unwrap();
+        for output in [
+            bilateral_filter(&image, 5, 2.0, 0.1),
+            bilateral_grid(&image, 2.0, 0.1),
+        ] {
+            assert!(output.get_data().iter().all(|x| (x - 0.25).abs() < 1e-6));
+        }
+    }
+
+    #[test]
+    fn large_range_sigma_is_gaussian_blur() {
+        let data = (0..12 * 10).map(|i| ((i * 37) % 11) as f64).collect();
+        let image = DynamicMatrix::new(12, 10, data).unwrap();
+        let output = bilateral_filter(&image, 5, 1.5, 1e9);
+        let expected: DynamicMatrix<f64> = convolve2d(&image, &kernel::gaussian(5, 1.5));
+
+        // Away from the borders every weight is inside the image
+        for row in 2..8 {
+            for col in 2..10 {
+                let (x, y) = (output.get_value(row, col), expected.get_value(row, col));
+                assert!((x.unwrap() - y.unwrap()).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test_case(1.0, 0.05)]
+    #[test_case(3.0, 0.1)]
+    fn edges_are_preserved(spatial_sigma: f64, range_sigma: f64) {
+        let image = step(40, 6);
+        let size = (6.0 * spatial_sigma) as usize | 1;
+        let exact = bilateral_filter(&image, size, spatial_sigma, range_sigma);
+        let approximate = bilateral_grid(&image, spatial_sigma, range_sigma);
+
+        for output in [exact, approximate] {
+            for (x, y) in output.get_data().iter().zip(image.get_data()) {
+                assert!((x - y).abs() < 0.02, "{} != {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn grid_approximates_filter() {
+        let data = (0..48 * 48)
+            .map(|i| ((i % 48) as f64 / 48.0 + (i / 48) as f64 / 96.0).sin())
+            .collect();
+        let image = DynamicMatrix::new(48, 48, data).unwrap();
+        let exact = bilateral_filter(&image, 13, 2.0, 0.2);
+        let approximate = bilateral_grid(&image, 2.0, 0.2);
+
+        let error = exact
+            .get_data()
+            .iter()
+            .zip(approximate.get_data())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max);
+        assert!(error < 0.02, "{}", error);
+    }
+
+    #[test]
+    fn subpixels() {
+        let image = DynamicMatrix::new(
+            3,
+            1,
+            vec![
+                SubPixels([0.0f32, 1.0]),
+                SubPixels([0.0, 1.0]),
+                SubPixels([1.0, 0.0]),
+            ],
+        )
+        .unwrap();
+        let output = bilateral_filter(&image, 3, 1.0, 0.1);
+        assert!((output.get_data()[1].0[0] - 0.0).abs() < 1e-6);
+        assert!((output.get_data()[2].0[1] - 0.0).abs() < 1e-6);
+    }
+
+    #[test_case(0.0, 0.1; "spatial")]
+    #[test_case(1.0, 0.0; "range")]
+    #[should_panic]
+    fn zero_sigma(spatial_sigma: f64, range_sigma: f64) {
+        bilateral_filter(&step(4, 4), 3, spatial_sigma, range_sigma);
+    }
+}
@@ -0,0 +1,285 @@
+//! Definition of the [`Complex`] number type, used for complex valued convolutions.
+
+use crate::{Channels, CompensatedAdd, Zero};
+#[cfg(feature = "std")]
+use crate::{DynamicMatrix, Matrix};
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A complex number, allowing complex valued kernels and images to be used in convolutions.
+///
+/// This struct implements the arithmetic traits required by the convolution functions, so it can
+/// be used as the data type for a [`Matrix`](crate::Matrix). Real numbers can also be multiplied
+/// by complex numbers, which allows real images to be convolved with complex kernels (such as a
+/// [gabor](crate::kernel::gabor) filter) into complex outputs.
+///
+/// # Example
+/// ```
+/// # use convolve2d::Complex;
+/// let a = Complex::new(1.0, 2.0);
+/// let b = Complex::new(3.0, -1.0);
+///
+/// assert_eq!(a + b, Complex::new(4.0, 1.0));
+/// assert_eq!(a * b, Complex::new(5.0, 5.0));
+/// assert_eq!(2.0 * a, Complex::new(2.0, 4.0));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Complex<T> {
+    /// The real part of the number
+    pub re: T,
+    /// The imaginary part of the number
+    pub im: T,
+}
+
+impl<T> Complex<T> {
+    /// Create a new complex number from its real and imaginary parts
+    pub const fn new(re: T, im: T) -> Self {
+        Self { re, im }
+    }
+}
+
+impl<T: Neg<Output = T>> Complex<T> {
+    /// Get the complex conjugate of this number
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::Complex;
+    /// assert_eq!(Complex::new(1, 2).conj(), Complex::new(1, -2));
+    /// ```
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+}
+
+macro_rules! complex_float_impl {
+    ($($t:ty),+) => {
+        $(
+            /// Multiply a real number by a complex number
+            impl Mul<Complex<$t>> for $t {
+                type Output = Complex<$t>;
+
+                #[inline]
+                fn mul(self, rhs: Complex<$t>) -> Complex<$t> {
+                    Complex::new(self * rhs.re, self * rhs.im)
+                }
+            }
+        )+
+    };
+}
+
+complex_float_impl!(f32, f64);
+
+#[cfg(feature = "std")]
+impl Complex<f64> {
+    /// Create a new complex number from its magnitude and phase, in radians.
+    ///
+    /// Requires the `std` feature.
+    pub fn from_polar(magnitude: f64, phase: f64) -> Self {
+        Self::new(magnitude * phase.cos(), magnitude * phase.sin())
+    }
+
+    /// Get the magnitude (absolute value) of this number.
+    ///
+    /// Requires the `std` feature.
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Get the phase (argument) of this number, in radians, in the range `-π..=π`.
+    ///
+    /// Requires the `std` feature.
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+}
+
+#[cfg(feature = "std")]
+impl DynamicMatrix<Complex<f64>> {
+    /// Get the magnitude of each element in the matrix.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{Complex, DynamicMatrix};
+    /// let mat = DynamicMatrix::new(2, 1, vec![
+    ///     Complex::new(3.0, 4.0), Complex::new(0.0, -2.0)
+    /// ]).unwrap();
+    /// assert_eq!(mat.magnitude(), DynamicMatrix::new(2, 1, vec![5.0, 2.0]).unwrap());
+    /// ```
+    pub fn magnitude(&self) -> DynamicMatrix<f64> {
+        let data = self.get_data().iter().map(|x| x.norm()).collect();
+        DynamicMatrix::new(self.get_width(), self.get_height(), data).unwrap()
+    }
+
+    /// Get the phase of each element in the matrix, in radians, in the range `-π..=π`.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{Complex, DynamicMatrix};
+    /// let mat = DynamicMatrix::new(2, 1, vec![
+    ///     Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)
+    /// ]).unwrap();
+    /// let expected = vec![0.0, std::f64::consts::PI];
+    /// assert_eq!(mat.phase(), DynamicMatrix::new(2, 1, expected).unwrap());
+    /// ```
+    pub fn phase(&self) -> DynamicMatrix<f64> {
+        let data = self.get_data().iter().map(|x| x.arg()).collect();
+        DynamicMatrix::new(self.get_width(), self.get_height(), data).unwrap()
+    }
+}
+
+impl<T: Add<Output = T>> Add for Complex<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Complex<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Complex<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl<T> Mul for Complex<T>
+where
+    T: Mul<Output = T> + Add<Output = T> + Sub<Output = T> + Clone,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.re.clone() * rhs.re.clone() - self.im.clone() * rhs.im.clone(),
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// Multiply a complex number by a real number. This allows complex images to be convolved with
+/// real kernels.
+impl<T: Mul<Output = T> + Clone> Mul<T> for Complex<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::new(self.re * rhs.clone(), self.im * rhs)
+    }
+}
+
+/// Divide a complex number by a real number. This allows a complex convolution to be used with a
+/// [`Kernel`](crate::Kernel)'s divisor.
+impl<T: Div<Output = T> + Clone> Div<T> for Complex<T> {
+    type Output = Self;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Self::new(self.re / rhs.clone(), self.im / rhs)
+    }
+}
+
+impl<T: CompensatedAdd> CompensatedAdd for Complex<T> {
+    fn compensated_add(self, rhs: Self, compensation: &mut Self) -> Self {
+        Self::new(
+            self.re.compensated_add(rhs.re, &mut compensation.re),
+            self.im.compensated_add(rhs.im, &mut compensation.im),
+        )
+    }
+}
+
+impl<T: Zero> Zero for Complex<T> {
+    fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.im.is_zero()
+    }
+}
+
+impl<T: Channels> Channels for Complex<T> {
+    const CHANNELS: usize = 2 * T::CHANNELS;
+
+    fn channel(&self, index: usize) -> f64 {
+        if index < T::CHANNELS {
+            self.re.channel(index)
+        } else {
+            self.im.channel(index - T::CHANNELS)
+        }
+    }
+
+    fn from_channels(mut channel: impl FnMut(usize) -> f64) -> Self {
+        let re = T::from_channels(&mut channel);
+        let im = T::from_channels(|index| channel(index + T::CHANNELS));
+        Self { re, im }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Complex;
+    use crate::{write_convolution, StaticMatrix, SubPixels};
+
+    #[test]
+    fn test_mul() {
+        let i = Complex::new(0, 1);
+        assert_eq!(i * i, Complex::new(-1, 0));
+        assert_eq!(Complex::new(2, 3) * 2, Complex::new(4, 6));
+    }
+
+    #[test]
+    fn test_div() {
+        assert_eq!(Complex::new(4.0, 6.0) / 2.0, Complex::new(2.0, 3.0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_polar() {
+        let c = Complex::from_polar(2.0, core::f64::consts::FRAC_PI_2);
+        assert!((c.norm() - 2.0).abs() < 1e-12);
+        assert!((c.arg() - core::f64::consts::FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn real_image_complex_kernel() {
+        let img = StaticMatrix::new(3, 1, [1.0, 2.0, 3.0]).unwrap();
+        let kernel = StaticMatrix::new(
+            3,
+            1,
+            [
+                Complex::new(1.0, 0.0),
+                Complex::new(0.0, 0.0),
+                Complex::new(0.0, 1.0),
+            ],
+        )
+        .unwrap();
+        let mut output = StaticMatrix::new(3, 1, [Complex::default(); 3]).unwrap();
+
+        write_convolution(&img, &kernel, &mut output);
+
+        let expected = [
+            Complex::new(0.0, 2.0),
+            Complex::new(1.0, 3.0),
+            Complex::new(2.0, 0.0),
+        ];
+        assert_eq!(output.into_parts().2, expected);
+    }
+
+    #[test]
+    fn subpixel_image_complex_kernel() {
+        let img = StaticMatrix::new(1, 1, [SubPixels([1.0, 2.0])]).unwrap();
+        let kernel = StaticMatrix::new(1, 1, [Complex::new(0.5, -1.0)]).unwrap();
+        let mut output = StaticMatrix::new(1, 1, [SubPixels::default()]).unwrap();
+
+        write_convolution(&img, &kernel, &mut output);
+
+        let expected = SubPixels([Complex::new(0.5, -1.0), Complex::new(1.0, -2.0)]);
+        assert_eq!(output.into_parts().2, [expected]);
+    }
+}
@@ -1,8 +1,9 @@
-//! Definitions of the two convolution functions provided by the library
+//! Definitions of the convolution functions provided by the library
 
+use crate::kernel::{default_anchor, Kernel, Tap};
 use crate::matrix::{FlippedMatrix, Matrix, MatrixMut};
-use crate::{SaturatingAdd, SaturatingMul};
-use core::ops::{Add, Mul};
+use crate::{CompensatedAdd, MaybeSend, MaybeSync, SaturatingAdd, SaturatingMul, Zero};
+use core::ops::{Add, AddAssign, Div, Mul, Sub};
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
@@ -22,6 +23,10 @@ use std::vec;
 /// scenarios, or in contexts in which greater control is needed, `write_convolution` may still be
 /// useful
 ///
+/// Each kernel value is always applied directly, whatever the size or shape of the kernel. To pick
+/// a faster [`Strategy`](crate::Strategy) for large or separable kernels, use
+/// [`convolve2d_auto`](crate::convolve2d_auto) or a [`StrategyPlan`](crate::StrategyPlan).
+///
 /// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
 ///
 /// # Example
@@ -53,9 +58,9 @@ use std::vec;
 #[cfg(feature = "std")]
 pub fn convolve2d<T, K, O>(image: &impl Matrix<T>, kernel: &impl Matrix<K>) -> DynamicMatrix<O>
 where
-    T: Mul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: Add<Output = O> + Default + Clone + Send,
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
 {
     let allocation = image.get_width() * image.get_height();
     let mut out = DynamicMatrix::new(
@@ -76,6 +81,9 @@ where
 /// While this function avoids allocations, and is therefore slightly faster, you may prefer the
 /// [`convolve2d`] function for a more idiomatic approach.
 ///
+/// Every element of the kernel is applied, including those which are zero. To skip them, use
+/// [`write_convolution_skip_zeros`], or [`write_convolution_sparse`] for very sparse kernels.
+///
 /// # Example
 /// ```
 /// use convolve2d::{write_convolution, StaticMatrix};
@@ -108,36 +116,107 @@ pub fn write_convolution<T, K, O>(
     kernel: &impl Matrix<K>,
     out: &mut impl MatrixMut<O>,
 ) where
-    T: Mul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: Add<Output = O> + Clone + Send,
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
 {
-    // Flip the kernel, as is the custom for convolutions
-    let kernel = FlippedMatrix(kernel);
-
-    let kernel_stride_x = (kernel.get_width() >> 1) as isize;
-    let kernel_stride_y = (kernel.get_height() >> 1) as isize;
-
-    for row in 0..kernel.get_height() {
-        // Calculate how many rows there are between the top of the image and the top of the kernel.
-        let rows_off_center = row as isize - kernel_stride_y;
+    let anchor = default_anchor(kernel);
+    for (alignment, value) in taps(kernel, anchor, image.get_width()) {
+        // Apply this kernel value and add to the buffer
+        update_buffer(image.get_data(), value, alignment, out.get_data_mut());
+    }
+}
 
-        for col in 0..kernel.get_width() {
-            // Calculate how many columns there are between the left side of the image and the left
-            // side of the kernel
-            let cols_off_center = col as isize - kernel_stride_x;
+/// Perform a 2D convolution on the specified image with the provided kernel, skipping the kernel's
+/// zero valued elements.
+///
+/// This function is a convient interface for the [`write_convolution_skip_zeros`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, convolve2d_skip_zeros, kernel, DynamicMatrix};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// let laplacian = kernel::laplacian::cross::<i32>();
+/// let output: DynamicMatrix<i32> = convolve2d_skip_zeros(&mat, &laplacian);
+/// assert_eq!(output, convolve2d(&mat, &laplacian));
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_skip_zeros<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &impl Matrix<K>,
+) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Zero + Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_convolution_skip_zeros(image, kernel, &mut out);
+    out
+}
 
-            // Determine the number of elements that the image row needs to be shifted
-            let alignment = rows_off_center * image.get_width() as isize + cols_off_center;
-
-            // Apply this kernel value and add to the buffer
-            update_buffer(
-                image.get_data(),
-                kernel.get_value(row, col).unwrap().clone(),
-                alignment,
-                out.get_data_mut(),
-            );
-        }
+/// Write the convolution of the provided image and kernel into the specified buffer, skipping the
+/// kernel's zero valued elements.
+///
+/// Each zero valued element of the kernel would otherwise cost a full pass over the image, so
+/// kernels with many zeros (such as laplacian or sobel kernels) are applied faster. Unlike
+/// [`write_convolution_sparse`], no list of taps needs to be built, so this works without the
+/// `std` feature.
+///
+/// Skipping zeros changes the result when the image holds values for which `x * 0` is not zero,
+/// such as infinite or NaN floats. Use [`write_convolution`] if those must propagate.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_skip_zeros, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 1, [1.0, f64::INFINITY, 3.0]).unwrap();
+/// let kernel = StaticMatrix::new(3, 1, [1.0, 0.0, 1.0]).unwrap();
+///
+/// let mut output = StaticMatrix::new(3, 1, [0.0; 3]).unwrap();
+/// write_convolution_skip_zeros(&mat, &kernel, &mut output);
+/// let expected = StaticMatrix::new(3, 1, [f64::INFINITY, 4.0, f64::INFINITY]).unwrap();
+/// assert_eq!(output, expected);
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_skip_zeros<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &impl Matrix<K>,
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Zero + Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    let anchor = default_anchor(kernel);
+    let taps = tap_refs(kernel, anchor, image.get_width()).filter(|(_, value)| !value.is_zero());
+    for (alignment, value) in taps {
+        update_buffer(
+            image.get_data(),
+            value.clone(),
+            alignment,
+            out.get_data_mut(),
+        );
     }
 }
 
@@ -183,9 +262,9 @@ pub fn convolve2d_saturating<T, K, O>(
     kernel: &impl Matrix<K>,
 ) -> DynamicMatrix<O>
 where
-    T: SaturatingMul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: SaturatingAdd<Output = O> + Default + Clone + Send,
+    T: SaturatingMul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: SaturatingAdd<Output = O> + Default + Clone + MaybeSend,
 {
     let allocation = image.get_width() * image.get_height();
     let mut out = DynamicMatrix::new(
@@ -238,105 +317,1015 @@ pub fn write_convolution_saturating<T, K, O>(
     kernel: &impl Matrix<K>,
     out: &mut impl MatrixMut<O>,
 ) where
-    T: SaturatingMul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: SaturatingAdd<Output = O> + Clone + Send,
+    T: SaturatingMul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: SaturatingAdd<Output = O> + Clone + MaybeSend,
 {
-    // Flip the kernel, as is the custom for convolutions
-    let kernel = FlippedMatrix(kernel);
-
-    let kernel_stride_x = (kernel.get_width() >> 1) as isize;
-    let kernel_stride_y = (kernel.get_height() >> 1) as isize;
-
-    for row in 0..kernel.get_height() {
-        // Calculate how many rows there are between the top of the image and the top of the kernel.
-        let rows_off_center = row as isize - kernel_stride_y;
-
-        for col in 0..kernel.get_width() {
-            // Calculate how many columns there are between the left side of the image and the left
-            // side of the kernel
-            let cols_off_center = col as isize - kernel_stride_x;
-
-            // Determine the number of elements that the image row needs to be shifted
-            let alignment = rows_off_center * image.get_width() as isize + cols_off_center;
-
-            // Apply this kernel value and add to the buffer
-            update_buffer_saturating(
-                image.get_data(),
-                kernel.get_value(row, col).unwrap().clone(),
-                alignment,
-                out.get_data_mut(),
-            );
-        }
+    let anchor = default_anchor(kernel);
+    for (alignment, value) in taps(kernel, anchor, image.get_width()) {
+        // Apply this kernel value and add to the buffer
+        update_buffer_saturating(image.get_data(), value, alignment, out.get_data_mut());
     }
 }
 
-/// Convert the provided alignment to padding and choke values.
+/// Perform a 2D convolution on the specified image with the provided kernel, using compensated
+/// summation and a deterministic order of operations.
 ///
-/// If the provided alignment is positive, that implies that we need to pad our output stream. If
-/// the provided alignment is negative, that implies we need to choke up on our output stream,
-/// throwing away the first `n` elements.
-fn alignment_to_choke_padding(alignment: isize) -> (usize, usize) {
-    // Use the alignment calculation to determine our choke and padding numbers
-    let mut choke = 0;
-    let mut padding = 0;
-    if alignment < 0 {
-        choke = alignment.unsigned_abs();
-    } else {
-        padding = alignment as usize;
-    }
-    (choke, padding)
+/// This function is a convient interface for the [`write_convolution_compensated`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d_compensated, DynamicMatrix, Matrix};
+/// let mat = DynamicMatrix::new(4, 1, vec![1.0, 1.0, 1.0, 1.0]).unwrap();
+/// let kernel = DynamicMatrix::new(4, 1, vec![1.0, 1e100, 1.0, -1e100]).unwrap();
+///
+/// let output = convolve2d_compensated(&mat, &kernel);
+/// assert_eq!(output.get_value(0, 1), Some(&2.0));
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_compensated<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &(impl Matrix<K> + MaybeSync),
+) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: CompensatedAdd + Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_convolution_compensated(image, kernel, &mut out);
+    out
 }
 
-/// Update the output buffer, multiplying the image by the kernel value and adding it to the
-/// buffer at the specified alignment.
-fn update_buffer<T, K, O>(image: &[T], kernel_value: K, alignment: isize, buf: &mut [O])
-where
-    T: Mul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: Add<Output = O> + Clone + Send,
+/// Write the convolution of the provided image and kernel into the specified buffer, using
+/// compensated summation and a deterministic order of operations.
+///
+/// Floating point addition is not associative, so large kernel values of opposite signs can
+/// cancel each other out and lose the contributions of smaller values. This function uses
+/// Neumaier summation (see [`CompensatedAdd`]) to recover the lost precision.
+///
+/// Each output value is accumulated by a single thread, applying the kernel values in the same
+/// order as [`write_convolution`]. This means that the result is bitwise identical whether or not
+/// the `rayon` feature is enabled, and regardless of the number of threads used.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_compensated, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 1, [1.0f32, 1.0, 1.0]).unwrap();
+/// let kernel = StaticMatrix::new(3, 1, [1e20, 1.0, -1e20]).unwrap();
+///
+/// let mut output = StaticMatrix::new(3, 1, [0.0; 3]).unwrap();
+/// write_convolution_compensated(&mat, &kernel, &mut output);
+/// assert_eq!(output.into_parts().2, [-1e20, 1.0, 1e20]);
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_compensated<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &(impl Matrix<K> + MaybeSync),
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: CompensatedAdd + Add<Output = O> + Default + Clone + MaybeSend,
 {
-    let (choke, padding) = alignment_to_choke_padding(alignment);
+    let anchor = default_anchor(kernel);
+    let width = image.get_width();
+    let image = image.get_data();
 
     #[cfg(not(feature = "rayon"))]
-    let image_iter = image.iter();
+    let buf_iter = out.get_data_mut().iter_mut();
     #[cfg(feature = "rayon")]
-    let image_iter = image.par_iter();
+    let buf_iter = out.get_data_mut().par_iter_mut();
 
-    #[cfg(not(feature = "rayon"))]
-    let buf_iter = buf.iter_mut();
-    #[cfg(feature = "rayon")]
-    let buf_iter = buf.par_iter_mut();
+    buf_iter.enumerate().for_each(|(i, a)| {
+        let mut compensation = O::default();
+        let mut sum = a.clone();
+        for (alignment, value) in taps(kernel, anchor, width) {
+            let source = i as isize - alignment;
+            if let Some(x) = usize::try_from(source).ok().and_then(|i| image.get(i)) {
+                sum = sum.compensated_add(x.clone() * value, &mut compensation);
+            }
+        }
+        *a = sum + compensation;
+    });
+}
 
-    image_iter
-        .map(|x| x.clone() * kernel_value.clone())
-        .skip(choke)
-        .zip(buf_iter.skip(padding))
-        .for_each(|(n, a)| *a = a.clone() + n)
+/// Perform a 2D convolution on the specified image with the provided kernel, using arithmetic on
+/// references.
+///
+/// This function is a convient interface for the [`write_convolution_ref`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d_ref, DynamicMatrix};
+/// let mat = DynamicMatrix::new(3, 1, vec![1, 2, 3]).unwrap();
+/// let kernel = DynamicMatrix::new(3, 1, vec![1, 0, -1]).unwrap();
+///
+/// let output = convolve2d_ref(&mat, &kernel);
+/// assert_eq!(output, DynamicMatrix::new(3, 1, vec![-2, -2, 2]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_ref<T, K, O>(image: &impl Matrix<T>, kernel: &impl Matrix<K>) -> DynamicMatrix<O>
+where
+    for<'a> &'a T: Mul<&'a K, Output = O>,
+    T: MaybeSync,
+    K: MaybeSync,
+    O: AddAssign + Default + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let data = (0..allocation).map(|_| O::default()).collect();
+    let mut out = DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap();
+    write_convolution_ref(image, kernel, &mut out);
+    out
 }
 
-/// Update the output buffer, multiplying the image by the kernel value and adding it to the
-/// buffer at the specified alignment.
-fn update_buffer_saturating<T, K, O>(image: &[T], kernel_value: K, alignment: isize, buf: &mut [O])
+/// Write the convolution of the provided image and kernel into the specified buffer, using
+/// arithmetic on references.
+///
+/// Unlike [`write_convolution`], this function never clones image or kernel values. Instead, it
+/// requires that `&T * &K` is defined, and adds the products to the output in place using
+/// [`AddAssign`]. This makes it suitable for element types which are expensive to clone, such as
+/// arbitrary precision or exact rational numbers, and even for types which can't be cloned at all.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_ref, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+///
+/// let kernel = StaticMatrix::new(3, 3, [
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+/// write_convolution_ref(&mat, &kernel, &mut output);
+/// assert_eq!(output, StaticMatrix::new(3, 3, [9, 8, 7, 6, 5, 4, 3, 2, 1]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_ref<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &impl Matrix<K>,
+    out: &mut impl MatrixMut<O>,
+) where
+    for<'a> &'a T: Mul<&'a K, Output = O>,
+    T: MaybeSync,
+    K: MaybeSync,
+    O: AddAssign + MaybeSend,
+{
+    let anchor = default_anchor(kernel);
+    for (alignment, value) in tap_refs(kernel, anchor, image.get_width()) {
+        // Apply this kernel value and add to the buffer
+        update_buffer_ref(image.get_data(), value, alignment, out.get_data_mut());
+    }
+}
+
+/// Perform a 2D convolution on the specified image with the provided [`Kernel`], applying its
+/// divisor, bias, and anchor.
+///
+/// This function is a convient interface for the [`write_convolution_kernel`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d_kernel, DynamicMatrix, Kernel, Matrix, StaticMatrix};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     16, 16, 16,
+///     16, 32, 16,
+///     16, 16, 16,
+/// ]).unwrap();
+///
+/// let kernel = Kernel::new(StaticMatrix::new(3, 3, [1, 2, 1, 2, 4, 2, 1, 2, 1]).unwrap())
+///     .with_divisor(16)
+///     .with_bias(100);
+///
+/// let output = convolve2d_kernel(&mat, &kernel);
+/// assert_eq!(output.get_value(1, 1), Some(&120));
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_kernel<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &Kernel<K, impl Matrix<K>>,
+) -> DynamicMatrix<O>
 where
-    T: SaturatingMul<K, Output = O> + Clone + Send + Sync,
-    K: Clone + Send + Sync,
-    O: SaturatingAdd<Output = O> + Clone + Send,
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Add<K, Output = O> + Div<K, Output = O> + Default + Clone + MaybeSend,
 {
-    let (choke, padding) = alignment_to_choke_padding(alignment);
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_convolution_kernel(image, kernel, &mut out);
+    out
+}
 
-    #[cfg(not(feature = "rayon"))]
-    let image_iter = image.iter();
-    #[cfg(feature = "rayon")]
-    let image_iter = image.par_iter();
+/// Write the convolution of the provided image and [`Kernel`] into the specified buffer, applying
+/// the kernel's divisor, bias, and anchor.
+///
+/// As with [`write_convolution`], the weighted sum is added to the values already in `out`. The
+/// divisor and bias are then applied to each element of `out`.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_kernel, Kernel, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 4, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+///
+/// let kernel = Kernel::new(StaticMatrix::new(2, 2, [1, 2, 3, 4]).unwrap())
+///     .with_divisor(2);
+///
+/// let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+/// write_convolution_kernel(&mat, &kernel, &mut output);
+/// assert_eq!(output, StaticMatrix::new(3, 3, [8, 6, 0, 4, 2, 0, 0, 0, 0]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_kernel<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &Kernel<K, impl Matrix<K>>,
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Add<K, Output = O> + Div<K, Output = O> + Clone + MaybeSend,
+{
+    for (alignment, value) in taps(kernel.matrix(), kernel.anchor(), image.get_width()) {
+        update_buffer(image.get_data(), value, alignment, out.get_data_mut());
+    }
+
+    let divisor = kernel.divisor();
+    let bias = kernel.bias();
+    if divisor.is_none() && bias.is_none() {
+        return;
+    }
 
     #[cfg(not(feature = "rayon"))]
-    let buf_iter = buf.iter_mut();
+    let buf_iter = out.get_data_mut().iter_mut();
     #[cfg(feature = "rayon")]
-    let buf_iter = buf.par_iter_mut();
+    let buf_iter = out.get_data_mut().par_iter_mut();
 
-    image_iter
-        .map(|x| x.clone().saturating_mul(kernel_value.clone()))
+    buf_iter.for_each(|a| {
+        if let Some(d) = divisor {
+            *a = a.clone() / d.clone();
+        }
+        if let Some(b) = bias {
+            *a = a.clone() + b.clone();
+        }
+    });
+}
+
+/// Perform a 2D convolution on the specified image with the provided sparse kernel.
+///
+/// This function is a convient interface for the [`write_convolution_sparse`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d_sparse, DynamicMatrix, Tap};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// // The difference between each pixel and the pixel two rows below it
+/// let taps = [Tap::new(0, 0, 1), Tap::new(2, 0, -1)];
+///
+/// let output = convolve2d_sparse(&mat, &taps);
+/// assert_eq!(output, DynamicMatrix::new(3, 3, vec![-6, -6, -6, 4, 5, 6, 7, 8, 9]).unwrap());
+/// ```
+#[cfg(feature = "std")]
+pub fn convolve2d_sparse<T, K, O>(image: &impl Matrix<T>, taps: &[Tap<K>]) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_convolution_sparse(image, taps, &mut out);
+    out
+}
+
+/// Write the convolution of the provided image and sparse kernel into the specified buffer.
+///
+/// A sparse kernel is described as a slice of [`Tap`]s, each giving the position of one kernel
+/// element relative to the anchor, along with its weight. Only the provided taps are applied, so
+/// the cost of the convolution is proportional to the number of taps rather than to the area of
+/// the kernel. This is useful for hand-written stencils in which most of the kernel is zero.
+///
+/// Sparse kernels can be generated from an existing kernel matrix using
+/// [`kernel::sparse`](crate::kernel::sparse), in which case the result is the same as that of
+/// [`write_convolution`].
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_sparse, StaticMatrix, Tap};
+/// let mat = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+///
+/// let taps = [Tap::new(-1, -1, 1), Tap::new(1, 1, 2)];
+///
+/// let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+/// write_convolution_sparse(&mat, &taps, &mut output);
+/// assert_eq!(output, StaticMatrix::new(3, 3, [2, 0, 0, 0, 0, 0, 0, 0, 1]).unwrap());
+/// ```
+pub fn write_convolution_sparse<T, K, O>(
+    image: &impl Matrix<T>,
+    taps: &[Tap<K>],
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    let width = image.get_width() as isize;
+    for tap in taps {
+        // The output is shifted in the opposite direction of the tap's offset
+        let alignment = -(tap.row * width + tap.col);
+        update_buffer(
+            image.get_data(),
+            tap.weight.clone(),
+            alignment,
+            out.get_data_mut(),
+        );
+    }
+}
+
+/// Perform a 2D convolution on the specified image with the provided kernel, taking advantage of
+/// any symmetry in the kernel.
+///
+/// This function is a convient interface for the [`write_convolution_symmetric`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, convolve2d_symmetric, kernel, DynamicMatrix};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// let sobel = kernel::sobel::x::<i32>();
+/// let output: DynamicMatrix<i32> = convolve2d_symmetric(&mat, &sobel);
+/// assert_eq!(output, convolve2d(&mat, &sobel));
+/// ```
+#[cfg(feature = "std")]
+pub fn convolve2d_symmetric<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &impl Matrix<K>,
+) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Default
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Add<Output = K> + PartialEq + Zero + Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_convolution_symmetric(image, kernel, &mut out);
+    out
+}
+
+/// Write the convolution of the provided image and kernel into the specified buffer, taking
+/// advantage of any symmetry in the kernel.
+///
+/// When a kernel is [symmetric](crate::kernel::symmetry), each pair of mirrored image values
+/// shares the same kernel value. Rather than multiplying each image value by the kernel value
+/// separately, the image values are added together (or subtracted, for antisymmetric kernels)
+/// before being multiplied. This roughly halves the number of multiplications needed for common
+/// kernels, such as those produced by the [`kernel`](crate::kernel) module.
+///
+/// Since image values are added together before being multiplied, the image type `T` must be able
+/// to hold the sum (or difference) of two image values without overflowing. For example, you may
+/// want to convert a `u8` image to `i32` before calling this function. If the kernel has no
+/// useful symmetry, this function falls back to [`write_convolution`].
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_symmetric, kernel, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+///
+/// let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+/// write_convolution_symmetric(&mat, &kernel::sobel::y::<i32>(), &mut output);
+/// assert_eq!(output, StaticMatrix::new(3, 3, [-1, -2, -1, 0, 0, 0, 1, 2, 1]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_symmetric<T, K, O>(
+    image: &impl Matrix<T>,
+    kernel: &impl Matrix<K>,
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Default
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Add<Output = K> + PartialEq + Zero + Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    let Some(symmetry) = crate::kernel::symmetry(kernel) else {
+        return write_convolution(image, kernel, out);
+    };
+
+    let size = (kernel.get_width(), kernel.get_height());
+    let (anchor_row, anchor_col) = default_anchor(kernel);
+    let width = image.get_width() as isize;
+    let alignment = |(row, col): (usize, usize)| {
+        (anchor_row as isize - row as isize) * width + (anchor_col as isize - col as isize)
+    };
+
+    for row in 0..kernel.get_height() {
+        for col in 0..kernel.get_width() {
+            let value = kernel.get_value(row, col).unwrap();
+            let pair = symmetry.mirror.pair((row, col), size);
+
+            // Each pair is only applied once, from the value which comes first
+            if (row, col) > pair {
+                continue;
+            } else if (row, col) == pair {
+                update_buffer(
+                    image.get_data(),
+                    value.clone(),
+                    alignment(pair),
+                    out.get_data_mut(),
+                );
+            } else {
+                update_buffer_pair(
+                    image.get_data(),
+                    value.clone(),
+                    (alignment((row, col)), alignment(pair)),
+                    symmetry.antisymmetric,
+                    out.get_data_mut(),
+                );
+            }
+        }
+    }
+}
+
+/// Convolve the specified image with each of the provided kernels, reading the image only once.
+///
+/// This function is a convient interface for the [`write_convolution_bank`] function,
+/// automatically generating a new allocation for each convolution. One output is returned for each
+/// kernel, in the same order as the kernels.
+///
+/// With the `rayon` feature enabled, the image is split between threads, with each thread applying
+/// every kernel to its portion of the image.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, convolve2d_bank, kernel, DynamicMatrix};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// let kernels = [kernel::sobel::x::<i32>(), kernel::sobel::y(), kernel::laplacian::full()];
+/// let outputs: Vec<DynamicMatrix<i32>> = convolve2d_bank(&mat, &kernels);
+///
+/// assert_eq!(outputs.len(), 3);
+/// for (output, kernel) in outputs.iter().zip(&kernels) {
+///     assert_eq!(output, &convolve2d(&mat, kernel));
+/// }
+/// ```
+///
+/// # Panics
+/// If any kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_bank<T, K, O>(
+    image: &impl Matrix<T>,
+    kernels: &[impl Matrix<K>],
+) -> Vec<DynamicMatrix<O>>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let width = image.get_width();
+    let height = image.get_height();
+    let data = image.get_data();
+    let taps: Vec<Vec<(isize, K)>> = kernels
+        .iter()
+        .map(|k| taps(k, default_anchor(k), width).collect())
+        .collect();
+
+    // Each pixel's results are stored together, so that each thread only needs to read its portion
+    // of the image once.
+    let mut interleaved = vec![O::default(); width * height * kernels.len()];
+
+    #[cfg(not(feature = "rayon"))]
+    let pixel_iter = interleaved.chunks_mut(kernels.len().max(1));
+    #[cfg(feature = "rayon")]
+    let pixel_iter = interleaved.par_chunks_mut(kernels.len().max(1));
+
+    pixel_iter.enumerate().for_each(|(i, pixel)| {
+        for (acc, kernel_taps) in pixel.iter_mut().zip(&taps) {
+            *acc = accumulate_at(data, i, kernel_taps.iter().cloned(), acc.clone());
+        }
+    });
+
+    (0..kernels.len())
+        .map(|k| {
+            let data = interleaved
+                .iter()
+                .skip(k)
+                .step_by(kernels.len())
+                .cloned()
+                .collect();
+            DynamicMatrix::new(width, height, data).unwrap()
+        })
+        .collect()
+}
+
+/// Write the convolution of the provided image with each of the provided kernels into the
+/// corresponding output buffer, reading the image only once.
+///
+/// When several kernels are applied to the same image, such as when computing sobel gradients in
+/// both directions, applying each kernel separately means reading the whole image once per kernel.
+/// This function instead visits each pixel once, applying every kernel to its neighborhood before
+/// moving on to the next pixel. The result written to each output is the same as that of
+/// [`write_convolution`].
+///
+/// All kernels must be of the same type. To use kernels of different types together, use a slice
+/// of `&dyn Matrix<K>`. Unlike [`convolve2d_bank`], this function does not use `rayon`, as it
+/// writes to each of the output buffers in turn.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, write_convolution_bank, Matrix, StaticMatrix};
+/// let mat = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+///
+/// let sobel = kernel::sobel::x::<i32>();
+/// let identity = StaticMatrix::new(1, 1, [1]).unwrap();
+/// let kernels: [&dyn Matrix<i32>; 2] = [&sobel, &identity];
+///
+/// let mut outputs = [
+///     StaticMatrix::new(3, 3, [0; 9]).unwrap(),
+///     StaticMatrix::new(3, 3, [0; 9]).unwrap(),
+/// ];
+/// write_convolution_bank(&mat, &kernels, &mut outputs);
+/// assert_eq!(outputs[0], StaticMatrix::new(3, 3, [1, 0, -1, 2, 0, -2, 1, 0, -1]).unwrap());
+/// assert_eq!(outputs[1], mat);
+/// ```
+///
+/// # Panics
+/// If the number of kernels does not match the number of outputs, or if the dimensions of any
+/// output do not match those of the image.
+///
+/// If any kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_bank<T, K, O>(
+    image: &impl Matrix<T>,
+    kernels: &[impl Matrix<K>],
+    outputs: &mut [impl MatrixMut<O>],
+) where
+    T: Mul<K, Output = O> + Clone,
+    K: Clone,
+    O: Add<Output = O> + Clone,
+{
+    assert_eq!(
+        kernels.len(),
+        outputs.len(),
+        "Each kernel requires exactly one output"
+    );
+    assert!(
+        outputs
+            .iter()
+            .all(|out| out.get_width() == image.get_width()
+                && out.get_height() == image.get_height()),
+        "Output dimensions do not match the image"
+    );
+
+    let width = image.get_width();
+    let data = image.get_data();
+
+    // Work out where each kernel value lands once up front, when there is somewhere to keep it
+    #[cfg(feature = "std")]
+    let kernel_taps: Vec<Vec<(isize, K)>> = kernels
+        .iter()
+        .map(|k| taps(k, default_anchor(k), width).collect())
+        .collect();
+
+    for i in 0..data.len() {
+        for (k, out) in outputs.iter_mut().enumerate() {
+            #[cfg(feature = "std")]
+            let kernel_taps = kernel_taps[k].iter().cloned();
+            #[cfg(not(feature = "std"))]
+            let kernel_taps = taps(&kernels[k], default_anchor(&kernels[k]), width);
+
+            let out = &mut out.get_data_mut()[i];
+            *out = accumulate_at(data, i, kernel_taps, out.clone());
+        }
+    }
+}
+
+/// Convolve each of the provided images with the same kernel.
+///
+/// This function is a convient interface for the [`write_convolution_batch`] function,
+/// automatically generating a new allocation for each convolution. One output is returned for each
+/// image, in the same order as the images.
+///
+/// Naturally, as this function uses the `DynamicMatrix` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, convolve2d_batch, kernel, DynamicMatrix};
+/// let frames: Vec<DynamicMatrix<f64>> = (0..30)
+///     .map(|i| DynamicMatrix::new(4, 4, vec![i as f64; 16]).unwrap())
+///     .collect();
+///
+/// let blur = kernel::gaussian(3, 1.0);
+/// let outputs: Vec<DynamicMatrix<f64>> = convolve2d_batch(&frames, &blur);
+///
+/// assert_eq!(outputs.len(), 30);
+/// assert_eq!(outputs[7], convolve2d(&frames[7], &blur));
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve2d_batch<T, K, O>(
+    images: &[impl Matrix<T> + MaybeSync],
+    kernel: &impl Matrix<K>,
+) -> Vec<DynamicMatrix<O>>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend + MaybeSync,
+{
+    let mut outputs: Vec<DynamicMatrix<O>> = images
+        .iter()
+        .map(|image| {
+            let allocation = image.get_width() * image.get_height();
+            DynamicMatrix::new(
+                image.get_width(),
+                image.get_height(),
+                vec![O::default(); allocation],
+            )
+            .unwrap()
+        })
+        .collect();
+    write_convolution_batch(images, kernel, &mut outputs);
+    outputs
+}
+
+/// Write the convolution of each of the provided images with the same kernel into the
+/// corresponding output buffer.
+///
+/// The kernel is prepared once, then shared between all of the images. With the `rayon` feature
+/// enabled, the images are processed in parallel, with each convolution also being parallelized
+/// across the image. The images do not need to be the same size, but each output must be the same
+/// size as its image. The result written to each output is the same as that of
+/// [`write_convolution`].
+///
+/// This function requires the `std` feature, as the prepared kernel is stored on the heap.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, write_convolution_batch, StaticMatrix};
+/// let frames = [
+///     StaticMatrix::new(3, 3, [0, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap(),
+///     StaticMatrix::new(3, 3, [0, 0, 0, 0, 2, 0, 0, 0, 0]).unwrap(),
+/// ];
+///
+/// let mut outputs = [
+///     StaticMatrix::new(3, 3, [0; 9]).unwrap(),
+///     StaticMatrix::new(3, 3, [0; 9]).unwrap(),
+/// ];
+/// write_convolution_batch(&frames, &kernel::laplacian::cross::<i32>(), &mut outputs);
+/// assert_eq!(outputs[0], StaticMatrix::new(3, 3, [0, -1, 0, -1, 4, -1, 0, -1, 0]).unwrap());
+/// assert_eq!(outputs[1], StaticMatrix::new(3, 3, [0, -2, 0, -2, 8, -2, 0, -2, 0]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the number of images does not match the number of outputs.
+///
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kenrel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn write_convolution_batch<T, K, O>(
+    images: &[impl Matrix<T> + MaybeSync],
+    kernel: &impl Matrix<K>,
+    outputs: &mut [impl MatrixMut<O> + MaybeSend],
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    assert_eq!(
+        images.len(),
+        outputs.len(),
+        "Each image requires exactly one output"
+    );
+
+    // Taps are positioned relative to the anchor, so they can be shared between images of any
+    // width. Reversing them means they're applied in the same order as `write_convolution`.
+    let mut taps = crate::kernel::dense(kernel);
+    taps.reverse();
+
+    #[cfg(not(feature = "rayon"))]
+    let image_iter = images.iter().zip(outputs.iter_mut());
+    #[cfg(feature = "rayon")]
+    let image_iter = images.par_iter().zip(outputs.par_iter_mut());
+
+    image_iter.for_each(|(image, out)| write_convolution_sparse(image, &taps, out));
+}
+
+/// Add the contributions of each tap to the output value at the specified index, where each tap is
+/// an alignment and kernel value as produced by [`taps`].
+fn accumulate_at<T, K, O>(
+    image: &[T],
+    index: usize,
+    taps: impl Iterator<Item = (isize, K)>,
+    mut acc: O,
+) -> O
+where
+    T: Mul<K, Output = O> + Clone,
+    O: Add<Output = O>,
+{
+    for (alignment, value) in taps {
+        let source = index as isize - alignment;
+        if let Some(x) = usize::try_from(source).ok().and_then(|i| image.get(i)) {
+            acc = acc + x.clone() * value;
+        }
+    }
+    acc
+}
+
+/// Iterate over the values of the kernel, along with the alignment at which each value should be
+/// applied to an image of the specified width.
+///
+/// The `anchor` is the `(row, col)` position of the kernel element that sits on top of the output
+/// pixel.
+pub(crate) fn taps<'a, K: Clone + 'a, M: Matrix<K>>(
+    kernel: &'a M,
+    anchor: (usize, usize),
+    image_width: usize,
+) -> impl Iterator<Item = (isize, K)> + 'a {
+    tap_refs(kernel, anchor, image_width).map(|(alignment, value)| (alignment, value.clone()))
+}
+
+/// Iterate over references to the values of the kernel, along with the alignment at which each
+/// value should be applied to an image of the specified width.
+///
+/// This is identical to [`taps`], but avoids cloning the kernel values.
+fn tap_refs<'a, K: 'a, M: Matrix<K>>(
+    kernel: &'a M,
+    anchor: (usize, usize),
+    image_width: usize,
+) -> impl Iterator<Item = (isize, &'a K)> + 'a {
+    // Flip the kernel, as is the custom for convolutions
+    let kernel = FlippedMatrix(kernel);
+
+    // An empty kernel has no taps, so saturate rather than underflow
+    let kernel_stride_x = kernel.get_width().saturating_sub(anchor.1 + 1) as isize;
+    let kernel_stride_y = kernel.get_height().saturating_sub(anchor.0 + 1) as isize;
+
+    (0..kernel.get_height()).flat_map(move |row| {
+        // Calculate how many rows there are between the top of the image and the top of the kernel.
+        let rows_off_center = row as isize - kernel_stride_y;
+
+        (0..kernel.get_width()).map(move |col| {
+            // Calculate how many columns there are between the left side of the image and the left
+            // side of the kernel
+            let cols_off_center = col as isize - kernel_stride_x;
+
+            // Determine the number of elements that the image row needs to be shifted
+            let alignment = rows_off_center * image_width as isize + cols_off_center;
+            (alignment, kernel.get_ref(row, col).unwrap())
+        })
+    })
+}
+
+/// Convert the provided alignment to padding and choke values.
+///
+/// If the provided alignment is positive, that implies that we need to pad our output stream. If
+/// the provided alignment is negative, that implies we need to choke up on our output stream,
+/// throwing away the first `n` elements.
+fn alignment_to_choke_padding(alignment: isize) -> (usize, usize) {
+    // Use the alignment calculation to determine our choke and padding numbers
+    let mut choke = 0;
+    let mut padding = 0;
+    if alignment < 0 {
+        choke = alignment.unsigned_abs();
+    } else {
+        padding = alignment as usize;
+    }
+    (choke, padding)
+}
+
+/// Update the output buffer, multiplying the image by the kernel value and adding it to the
+/// buffer at the specified alignment.
+pub(crate) fn update_buffer<T, K, O>(image: &[T], kernel_value: K, alignment: isize, buf: &mut [O])
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    let (choke, padding) = alignment_to_choke_padding(alignment);
+
+    #[cfg(not(feature = "rayon"))]
+    let image_iter = image.iter();
+    #[cfg(feature = "rayon")]
+    let image_iter = image.par_iter();
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = buf.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = buf.par_iter_mut();
+
+    image_iter
+        .map(|x| x.clone() * kernel_value.clone())
+        .skip(choke)
+        .zip(buf_iter.skip(padding))
+        .for_each(|(n, a)| *a = a.clone() + n)
+}
+
+/// Update the output buffer, multiplying the image by the kernel value and adding it to the
+/// buffer at the specified alignment, without cloning any values.
+fn update_buffer_ref<T, K, O>(image: &[T], kernel_value: &K, alignment: isize, buf: &mut [O])
+where
+    for<'a> &'a T: Mul<&'a K, Output = O>,
+    T: MaybeSync,
+    K: MaybeSync,
+    O: AddAssign + MaybeSend,
+{
+    let (choke, padding) = alignment_to_choke_padding(alignment);
+
+    #[cfg(not(feature = "rayon"))]
+    let image_iter = image.iter();
+    #[cfg(feature = "rayon")]
+    let image_iter = image.par_iter();
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = buf.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = buf.par_iter_mut();
+
+    image_iter
+        .skip(choke)
+        .zip(buf_iter.skip(padding))
+        .for_each(|(x, a)| *a += x * kernel_value)
+}
+
+/// Update the output buffer, adding (or subtracting, if `antisymmetric` is set) the image values
+/// at each of the two alignments, then multiplying by the kernel value and adding it to the buffer.
+fn update_buffer_pair<T, K, O>(
+    image: &[T],
+    kernel_value: K,
+    alignments: (isize, isize),
+    antisymmetric: bool,
+    buf: &mut [O],
+) where
+    T: Mul<K, Output = O>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Default
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    // Get the image value that lands on the output at index `i` for the given alignment
+    let sample = |i: usize, alignment: isize| {
+        let index = i as isize - alignment;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| image.get(index))
+    };
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = buf.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = buf.par_iter_mut();
+
+    buf_iter.enumerate().for_each(|(i, a)| {
+        let first = sample(i, alignments.0).cloned();
+        let second = sample(i, alignments.1).cloned();
+        let combined = match (first, second, antisymmetric) {
+            (Some(x), Some(y), false) => x + y,
+            (Some(x), Some(y), true) => x - y,
+            (Some(x), None, _) | (None, Some(x), false) => x,
+            (None, Some(y), true) => T::default() - y,
+            (None, None, _) => return,
+        };
+        *a = a.clone() + combined * kernel_value.clone();
+    })
+}
+
+/// Update the output buffer, multiplying the image by the kernel value and adding it to the
+/// buffer at the specified alignment.
+pub(crate) fn update_buffer_saturating<T, K, O>(
+    image: &[T],
+    kernel_value: K,
+    alignment: isize,
+    buf: &mut [O],
+) where
+    T: SaturatingMul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: SaturatingAdd<Output = O> + Clone + MaybeSend,
+{
+    let (choke, padding) = alignment_to_choke_padding(alignment);
+
+    #[cfg(not(feature = "rayon"))]
+    let image_iter = image.iter();
+    #[cfg(feature = "rayon")]
+    let image_iter = image.par_iter();
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = buf.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = buf.par_iter_mut();
+
+    image_iter
+        .map(|x| x.clone().saturating_mul(kernel_value.clone()))
         .skip(choke)
         .zip(buf_iter.skip(padding))
         .for_each(|(n, a)| *a = a.clone().saturating_add(n))
@@ -345,7 +1334,13 @@ where
 #[cfg(test)]
 mod tests {
     use super::update_buffer;
-    use crate::{write_convolution, write_convolution_saturating, StaticMatrix};
+    use crate::{
+        kernel, write_convolution, write_convolution_bank, write_convolution_compensated,
+        write_convolution_kernel, write_convolution_ref, write_convolution_saturating,
+        write_convolution_skip_zeros, write_convolution_sparse, write_convolution_symmetric,
+        Kernel, Matrix, MatrixMut, StaticMatrix, SubPixels, Zero,
+    };
+    use core::ops::{AddAssign, Mul};
     use test_case::test_case;
 
     #[test_case(-5, [12, 14, 16, 18, 0, 0, 0, 0, 0]; "alignment_n5")]
@@ -398,4 +1393,271 @@ mod tests {
             StaticMatrix::new(3, 3, [128, 255, 255, 255, 255, 255, 255, 255, 255]).unwrap();
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn compensated_matches_write_convolution() {
+        let img =
+            StaticMatrix::new(4, 3, core::array::from_fn::<f64, 12, _>(|i| i as f64)).unwrap();
+        let kernel = StaticMatrix::new(3, 2, [1.0, -2.0, 0.0, 3.0, -4.0, 0.5]).unwrap();
+        let mut expected = StaticMatrix::new(4, 3, [1.0; 12]).unwrap();
+        let mut output = StaticMatrix::new(4, 3, [1.0; 12]).unwrap();
+
+        write_convolution(&img, &kernel, &mut expected);
+        write_convolution_compensated(&img, &kernel, &mut output);
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn compensated_cancellation() {
+        let img = StaticMatrix::new(2, 2, [SubPixels([1.0, 2.0]); 4]).unwrap();
+        let kernel = StaticMatrix::new(2, 2, [1e17, 1.0, -1e17, 1.0]).unwrap();
+        let mut output = StaticMatrix::new(2, 2, [SubPixels([0.0; 2]); 4]).unwrap();
+
+        write_convolution_compensated(&img, &kernel, &mut output);
+
+        assert_eq!(output.get_value(0, 0), Some(&SubPixels([2.0, 4.0])));
+    }
+
+    /// A number type which can't be cloned, to check that the by-reference API never clones
+    #[derive(Debug, Default, PartialEq)]
+    struct Exact(i64);
+
+    impl Mul<&Exact> for &Exact {
+        type Output = Exact;
+
+        fn mul(self, rhs: &Exact) -> Exact {
+            Exact(self.0 * rhs.0)
+        }
+    }
+
+    impl AddAssign for Exact {
+        fn add_assign(&mut self, rhs: Exact) {
+            self.0 += rhs.0;
+        }
+    }
+
+    impl Zero for Exact {
+        fn is_zero(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    #[test]
+    fn ref_matches_write_convolution() {
+        let data = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8];
+        let kernel_data = [2, 0, -7, 1, 8, -2];
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        write_convolution(
+            &StaticMatrix::new(4, 3, data).unwrap(),
+            &StaticMatrix::new(3, 2, kernel_data).unwrap(),
+            &mut expected,
+        );
+
+        let img = StaticMatrix::new(4, 3, data.map(Exact)).unwrap();
+        let kernel = StaticMatrix::new(3, 2, kernel_data.map(Exact)).unwrap();
+        let mut output = StaticMatrix::new(4, 3, core::array::from_fn(|_| Exact(0))).unwrap();
+        write_convolution_ref(&img, &kernel, &mut output);
+
+        assert_eq!(output.into_parts().2, expected.into_parts().2.map(Exact));
+    }
+
+    #[test]
+    fn kernel_matches_write_convolution() {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let kernel = StaticMatrix::new(2, 2, [1, -2, 3, -4]).unwrap();
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        let mut output = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+
+        write_convolution(&img, &kernel, &mut expected);
+        write_convolution_kernel(&img, &Kernel::new(kernel), &mut output);
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn kernel_exact_integer_blur() {
+        let img = StaticMatrix::new(3, 3, [SubPixels([16, 32]); 9]).unwrap();
+        let kernel = Kernel::new(StaticMatrix::new(3, 3, [1, 2, 1, 2, 4, 2, 1, 2, 1]).unwrap())
+            .with_divisor(16)
+            .with_bias(-1);
+        let mut output = StaticMatrix::new(3, 3, [SubPixels([0; 2]); 9]).unwrap();
+
+        write_convolution_kernel(&img, &kernel, &mut output);
+
+        assert_eq!(output.get_value(1, 1), Some(&SubPixels([15, 31])));
+    }
+
+    #[test]
+    fn kernel_anchor() {
+        let mut img = StaticMatrix::new(5, 5, [0; 25]).unwrap();
+        img.get_data_mut()[12] = 1;
+        let kernel = Kernel::new(StaticMatrix::new(3, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap())
+            .with_anchor(0, 0)
+            .unwrap();
+        let mut output = StaticMatrix::new(5, 5, [0; 25]).unwrap();
+
+        write_convolution_kernel(&img, &kernel, &mut output);
+
+        #[rustfmt::skip]
+        let expected = StaticMatrix::new(5, 5, [
+            9, 8, 7, 0, 0,
+            6, 5, 4, 0, 0,
+            3, 2, 1, 0, 0,
+            0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0,
+        ]).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn empty_kernel() {
+        let img = StaticMatrix::new(3, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+        let kernel = StaticMatrix::new(0, 0, [0; 0]).unwrap();
+        let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+
+        write_convolution(&img, &kernel, &mut output);
+        assert_eq!(output.get_data(), &[0; 9]);
+
+        #[cfg(feature = "std")]
+        assert_eq!(crate::convolve2d(&img, &kernel).get_data(), &[0; 9]);
+    }
+
+    #[test]
+    fn zero_taps_applied() {
+        let img = StaticMatrix::new(3, 1, [1.0, f64::INFINITY, 3.0]).unwrap();
+        let kernel = StaticMatrix::new(3, 1, [1.0, 0.0, 1.0]).unwrap();
+        let mut output = StaticMatrix::new(3, 1, [0.0; 3]).unwrap();
+
+        // `0 * inf` is NaN, which propagates just as it would without a convolution
+        write_convolution(&img, &kernel, &mut output);
+        assert!(output.get_value(0, 1).unwrap().is_nan());
+
+        // Sparse kernels only hold the non-zero values, so they skip it
+        let mut output = StaticMatrix::new(3, 1, [0.0; 3]).unwrap();
+        write_convolution_sparse(&img, &kernel::sparse(&kernel), &mut output);
+        assert_eq!(output.get_value(0, 1), Some(&4.0));
+
+        // As does the opt-in dense path
+        let mut output = StaticMatrix::new(3, 1, [0.0; 3]).unwrap();
+        write_convolution_skip_zeros(&img, &kernel, &mut output);
+        assert_eq!(output.get_value(0, 1), Some(&4.0));
+    }
+
+    #[test_case(kernel::laplacian::cross(); "laplacian")]
+    #[test_case(kernel::sobel::x(); "sobel")]
+    #[test_case(StaticMatrix::new(3, 3, [0; 9]).unwrap(); "zeros")]
+    fn skip_zeros_matches_write_convolution(kernel: StaticMatrix<i32, 9>) {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        write_convolution(&img, &kernel, &mut expected);
+
+        let mut output = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        write_convolution_skip_zeros(&img, &kernel, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sparse_matches_write_convolution() {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let laplacian: StaticMatrix<i32, 9> = kernel::laplacian::cross();
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        let mut output = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+
+        write_convolution(&img, &laplacian, &mut expected);
+        write_convolution_sparse(&img, &kernel::sparse(&laplacian), &mut output);
+
+        assert_eq!(output, expected);
+    }
+
+    #[test_case(kernel::sobel::x(); "sobel_x")]
+    #[test_case(kernel::sobel::y(); "sobel_y")]
+    #[test_case(kernel::laplacian::cross(); "laplacian_cross")]
+    #[test_case(kernel::laplacian::full(); "laplacian_full")]
+    fn symmetric_matches_write_convolution(kernel: StaticMatrix<i32, 9>) {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        let mut output = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+
+        write_convolution(&img, &kernel, &mut expected);
+        write_convolution_symmetric(&img, &kernel, &mut output);
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn symmetric_even_kernel() {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let kernel = StaticMatrix::new(4, 2, [1, 2, -2, -1, 3, 4, -4, -3]).unwrap();
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        let mut output = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+
+        write_convolution(&img, &kernel, &mut expected);
+        write_convolution_symmetric(&img, &kernel, &mut output);
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn bank_matches_write_convolution() {
+        let img = StaticMatrix::new(4, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let kernels: [StaticMatrix<i32, 9>; 3] = [
+            kernel::sobel::x(),
+            kernel::sobel::y(),
+            kernel::laplacian::full(),
+        ];
+        let mut outputs = [
+            StaticMatrix::new(4, 3, [0; 12]).unwrap(),
+            StaticMatrix::new(4, 3, [0; 12]).unwrap(),
+            StaticMatrix::new(4, 3, [0; 12]).unwrap(),
+        ];
+
+        write_convolution_bank(&img, &kernels, &mut outputs);
+
+        for (kernel, output) in kernels.iter().zip(&outputs) {
+            let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+            write_convolution(&img, kernel, &mut expected);
+            assert_eq!(output, &expected);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn bank_wrong_output_size() {
+        let img = StaticMatrix::new(2, 2, [1, 2, 3, 4]).unwrap();
+        let kernels = [kernel::sobel::x::<i32>(), kernel::sobel::y::<i32>()];
+        let mut outputs = [
+            StaticMatrix::new(2, 2, [0; 4]).unwrap(),
+            StaticMatrix::new(4, 1, [0; 4]).unwrap(),
+        ];
+        write_convolution_bank(&img, &kernels, &mut outputs);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bank_empty() {
+        let img = StaticMatrix::new(2, 2, [1, 2, 3, 4]).unwrap();
+        let kernels: [StaticMatrix<i32, 9>; 0] = [];
+        let outputs: std::vec::Vec<crate::DynamicMatrix<i32>> =
+            crate::convolve2d_bank(&img, &kernels);
+        assert!(outputs.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn batch_mixed_sizes() {
+        let images = [
+            crate::DynamicMatrix::new(3, 2, std::vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap(),
+            crate::DynamicMatrix::new(2, 3, std::vec![1.5, 2.5, 3.5, 4.5, 5.5, 6.5]).unwrap(),
+        ];
+        let blur = kernel::gaussian(3, 1.0);
+
+        let outputs: std::vec::Vec<crate::DynamicMatrix<f64>> =
+            crate::convolve2d_batch(&images, &blur);
+
+        for (image, output) in images.iter().zip(&outputs) {
+            assert_eq!(output, &crate::convolve2d(image, &blur));
+        }
+    }
 }
@@ -0,0 +1,315 @@
+//! Definitions of the one dimensional convolution functions provided by the library
+//!
+//! These functions apply kernels in the same orientation as their two dimensional counterparts,
+//! so convolving a signal in [`Mode::Same`] gives the same result as using
+//! [`convolve2d`](crate::convolve2d) on a matrix with a height of one.
+
+use crate::{Matrix, MaybeSend, MaybeSync};
+use core::ops::{Add, Mul};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// Re-import the standard library
+#[cfg(feature = "std")]
+use std::prelude::v1::*;
+#[cfg(feature = "std")]
+use std::vec;
+
+/// The portion of a one dimensional convolution to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Produce every output which overlaps the signal by at least one element. The output is
+    /// `kernel.len() - 1` elements longer than the signal.
+    Full,
+    /// Produce one output for each element of the signal, with the kernel centered on that element.
+    Same,
+    /// Produce only the outputs for which the kernel lies entirely within the signal. The output is
+    /// `kernel.len() - 1` elements shorter than the signal, or empty if the kernel is empty or
+    /// longer than the signal.
+    Valid,
+}
+
+impl Mode {
+    /// Get the length of the output produced when convolving a signal of length `signal_len` with
+    /// a kernel of length `kernel_len`.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::Mode;
+    /// assert_eq!(Mode::Full.output_len(10, 3), 12);
+    /// assert_eq!(Mode::Same.output_len(10, 3), 10);
+    /// assert_eq!(Mode::Valid.output_len(10, 3), 8);
+    /// ```
+    pub fn output_len(&self, signal_len: usize, kernel_len: usize) -> usize {
+        match self {
+            Self::Full | Self::Valid if signal_len == 0 || kernel_len == 0 => 0,
+            Self::Full => signal_len + kernel_len - 1,
+            Self::Same => signal_len,
+            Self::Valid => (signal_len + 1).saturating_sub(kernel_len),
+        }
+    }
+
+    /// Get the index of the signal element which lines up with the first kernel element for the
+    /// first output.
+    fn offset(&self, kernel_len: usize) -> isize {
+        match self {
+            Self::Full => -(kernel_len as isize - 1),
+            Self::Same => -((((kernel_len + 1) >> 1).saturating_sub(1)) as isize),
+            Self::Valid => 0,
+        }
+    }
+}
+
+/// A single row or column of a [`Matrix`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Line {
+    /// The row with the given index
+    Row(usize),
+    /// The column with the given index
+    Column(usize),
+}
+
+/// Perform a 1D convolution on the specified signal with the provided kernel.
+///
+/// This function is a convient interface for the [`write_convolution1d`] function, automatically
+/// generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function returns a `Vec`, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve1d, Mode};
+/// let signal = [0, 0, 1, 0, 0];
+/// let kernel = [1, 2, 3];
+///
+/// assert_eq!(convolve1d(&signal, &kernel, Mode::Full), vec![0, 0, 3, 2, 1, 0, 0]);
+/// assert_eq!(convolve1d(&signal, &kernel, Mode::Same), vec![0, 3, 2, 1, 0]);
+/// assert_eq!(convolve1d(&signal, &kernel, Mode::Valid), vec![3, 2, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn convolve1d<T, K, O>(signal: &[T], kernel: &[K], mode: Mode) -> Vec<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let mut out = vec![O::default(); mode.output_len(signal.len(), kernel.len())];
+    write_convolution1d(signal, kernel, mode, &mut out);
+    out
+}
+
+/// Write the 1D convolution of the provided signal and kernel into the specified buffer.
+///
+/// As with [`write_convolution`](crate::write_convolution), the result of the convolution is
+/// added to the values already in `out`. Elements outside of the signal are treated as zero.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution1d, Mode};
+/// let signal = [1.0, 2.0, 3.0, 4.0];
+/// let kernel = [0.5, 0.5];
+///
+/// let mut out = [0.0; 3];
+/// write_convolution1d(&signal, &kernel, Mode::Valid, &mut out);
+/// assert_eq!(out, [1.5, 2.5, 3.5]);
+/// ```
+///
+/// # Panics
+/// If the length of `out` is not [`mode.output_len(..)`](Mode::output_len) for the lengths of the
+/// signal and kernel.
+pub fn write_convolution1d<T, K, O>(signal: &[T], kernel: &[K], mode: Mode, out: &mut [O])
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    write_convolution1d_by(signal.len(), |i| &signal[i], kernel, mode, out)
+}
+
+/// Perform a 1D convolution on a single row or column of the specified image.
+///
+/// This function is a convient interface for the [`write_convolution1d_line`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function returns a `Vec`, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve1d_line, DynamicMatrix, Line, Mode};
+/// let mat = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// assert_eq!(convolve1d_line(&mat, Line::Row(1), &[1, -1], Mode::Valid), vec![-1, -1]);
+/// assert_eq!(convolve1d_line(&mat, Line::Column(2), &[1, -1], Mode::Valid), vec![-3, -3]);
+/// ```
+///
+/// # Panics
+/// If the row or column is outside of the image.
+#[cfg(feature = "std")]
+pub fn convolve1d_line<T, K, O>(
+    image: &(impl Matrix<T> + MaybeSync),
+    line: Line,
+    kernel: &[K],
+    mode: Mode,
+) -> Vec<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let len = match line {
+        Line::Row(_) => image.get_width(),
+        Line::Column(_) => image.get_height(),
+    };
+    let mut out = vec![O::default(); mode.output_len(len, kernel.len())];
+    write_convolution1d_line(image, line, kernel, mode, &mut out);
+    out
+}
+
+/// Write the 1D convolution of a single row or column of the provided image and kernel into the
+/// specified buffer.
+///
+/// This allows scanlines to be processed without copying them out of the image first.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution1d_line, Line, Mode, StaticMatrix};
+/// let mat = StaticMatrix::new(2, 3, [
+///     1, 2,
+///     3, 4,
+///     5, 6,
+/// ]).unwrap();
+///
+/// let mut out = [0; 3];
+/// write_convolution1d_line(&mat, Line::Column(1), &[1, 1, 1], Mode::Same, &mut out);
+/// assert_eq!(out, [6, 12, 10]);
+/// ```
+///
+/// # Panics
+/// If the row or column is outside of the image, or if the length of `out` is not
+/// [`mode.output_len(..)`](Mode::output_len) for the length of the row or column.
+pub fn write_convolution1d_line<T, K, O>(
+    image: &(impl Matrix<T> + MaybeSync),
+    line: Line,
+    kernel: &[K],
+    mode: Mode,
+    out: &mut [O],
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    match line {
+        Line::Row(row) => {
+            assert!(row < image.get_height(), "Row is outside of the image");
+            let width = image.get_width();
+            let data = &image.get_data()[row * width..(row + 1) * width];
+            write_convolution1d(data, kernel, mode, out)
+        }
+        Line::Column(col) => {
+            assert!(col < image.get_width(), "Column is outside of the image");
+            let get = |row| image.get_value(row, col).unwrap();
+            write_convolution1d_by(image.get_height(), get, kernel, mode, out)
+        }
+    }
+}
+
+/// Write the 1D convolution of the signal of length `len` described by `get` and the provided
+/// kernel into the specified buffer.
+fn write_convolution1d_by<'a, T, K, O>(
+    len: usize,
+    get: impl Fn(usize) -> &'a T + MaybeSync,
+    kernel: &[K],
+    mode: Mode,
+    out: &mut [O],
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync + 'a,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    assert_eq!(
+        out.len(),
+        mode.output_len(len, kernel.len()),
+        "Output length does not match the convolution mode"
+    );
+    let offset = mode.offset(kernel.len());
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = out.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = out.par_iter_mut();
+
+    buf_iter.enumerate().for_each(|(i, a)| {
+        for (j, value) in kernel.iter().enumerate() {
+            let index = i as isize + j as isize + offset;
+            if index < 0 || index as usize >= len {
+                continue;
+            }
+            *a = a.clone() + get(index as usize).clone() * value.clone();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_convolution1d, Mode};
+    use crate::{write_convolution, StaticMatrix};
+    use std::vec;
+    use test_case::test_case;
+
+    #[test]
+    fn same_matches_write_convolution() {
+        let signal = [3, 1, 4, 1, 5, 9, 2, 6];
+        let kernel = [2, -1, 0, 7];
+
+        let mut expected = StaticMatrix::new(8, 1, [0; 8]).unwrap();
+        write_convolution(
+            &StaticMatrix::new(8, 1, signal).unwrap(),
+            &StaticMatrix::new(4, 1, kernel).unwrap(),
+            &mut expected,
+        );
+
+        let mut out = [0; 8];
+        write_convolution1d(&signal, &kernel, Mode::Same, &mut out);
+        assert_eq!(out, expected.into_parts().2);
+    }
+
+    #[test_case(Mode::Full; "full")]
+    #[test_case(Mode::Same; "same")]
+    #[test_case(Mode::Valid; "valid")]
+    fn empty_signal(mode: Mode) {
+        let signal: [i32; 0] = [];
+        let mut out = [0; 0];
+        assert_eq!(mode.output_len(signal.len(), 3), 0);
+        write_convolution1d(&signal, &[1, 2, 3], mode, &mut out);
+    }
+
+    #[test]
+    fn kernel_longer_than_signal() {
+        let mut out = [0; 4];
+        write_convolution1d(&[1, 2], &[1, 1, 1], Mode::Full, &mut out);
+        assert_eq!(out, [1, 3, 3, 2]);
+        assert_eq!(Mode::Valid.output_len(2, 3), 0);
+    }
+
+    #[test_case(Mode::Full, 0; "full")]
+    #[test_case(Mode::Same, 4; "same")]
+    #[test_case(Mode::Valid, 0; "valid")]
+    fn empty_kernel(mode: Mode, len: usize) {
+        let kernel: [i32; 0] = [];
+        let mut out = vec![0; len];
+        assert_eq!(mode.output_len(4, 0), len);
+        write_convolution1d(&[1, 2, 3, 4], &kernel, mode, &mut out);
+        assert!(out.iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_output_length() {
+        let mut out = [0; 3];
+        write_convolution1d(&[1, 2, 3], &[1, 1], Mode::Full, &mut out);
+    }
+}
@@ -0,0 +1,247 @@
+//! Definitions of the volumetric convolution functions provided by the library
+//!
+//! These functions mirror their two dimensional counterparts, such as
+//! [`write_convolution`](crate::write_convolution), and share the same handling of borders and
+//! arithmetic. Since volumes are stored contiguously, each kernel value can be applied to the whole
+//! volume at once by shifting the volume data.
+
+use crate::convolution::{update_buffer, update_buffer_saturating};
+use crate::volume::{Volume, VolumeMut};
+use crate::{MaybeSend, MaybeSync, SaturatingAdd, SaturatingMul};
+use core::ops::{Add, Mul};
+
+// Re-import the standard library
+#[cfg(feature = "std")]
+use crate::DynamicVolume;
+#[cfg(feature = "std")]
+use std::vec;
+
+/// Perform a 3D convolution on the specified volume with the provided kernel.
+///
+/// This function is a convient interface for the [`write_convolution3d`] function, automatically
+/// generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicVolume` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve3d, kernel, DynamicVolume, Volume};
+/// let mut data = vec![0.0; 27];
+/// data[13] = 1.0;
+/// let vol = DynamicVolume::new(3, 3, 3, data).unwrap();
+///
+/// let output = convolve3d(&vol, &kernel::gaussian_3d(3, 1.0));
+/// let sum: f64 = output.get_data().iter().sum();
+/// assert!((sum - 1.0).abs() < 1e-9);
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all slice, row and column values
+/// in the ranges `0..kernel.get_depth()`, `0..kernel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve3d<T, K, O>(volume: &impl Volume<T>, kernel: &impl Volume<K>) -> DynamicVolume<O>
+where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    let mut out = DynamicVolume::new(
+        volume.get_width(),
+        volume.get_height(),
+        volume.get_depth(),
+        vec![O::default(); volume.get_data().len()],
+    )
+    .unwrap();
+    write_convolution3d(volume, kernel, &mut out);
+    out
+}
+
+/// Write the 3D convolution of the provided volume and kernel into the specified buffer.
+///
+/// As with [`write_convolution`](crate::write_convolution), the result of the convolution is
+/// added to the values already in `out`.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, write_convolution3d, StaticVolume, Volume, VolumeMut};
+/// let mut vol = StaticVolume::new(3, 3, 3, [0; 27]).unwrap();
+/// vol.get_data_mut()[13] = 1;
+///
+/// let mut output = StaticVolume::new(3, 3, 3, [0; 27]).unwrap();
+/// write_convolution3d(&vol, &kernel::sobel_3d::z::<i32>(), &mut output);
+/// assert_eq!(output.get_value(0, 1, 1), Some(&4));
+/// assert_eq!(output.get_value(2, 1, 1), Some(&-4));
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all slice, row and column values
+/// in the ranges `0..kernel.get_depth()`, `0..kernel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution3d<T, K, O>(
+    volume: &impl Volume<T>,
+    kernel: &impl Volume<K>,
+    out: &mut impl VolumeMut<O>,
+) where
+    T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Clone + MaybeSend,
+{
+    for (alignment, value) in taps_3d(kernel, volume.get_width(), volume.get_height()) {
+        // Apply this kernel value and add to the buffer
+        update_buffer(volume.get_data(), value, alignment, out.get_data_mut());
+    }
+}
+
+/// Perform a 3D convolution on the specified volume with the provided kernel, without integer
+/// overflow.
+///
+/// This function is a convient interface for the [`write_convolution3d_saturating`] function,
+/// automatically generating a new allocation in which to store the convolution.
+///
+/// Naturally, as this function uses the `DynamicVolume` type, it requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve3d_saturating, DynamicVolume, StaticVolume};
+/// let vol: DynamicVolume<u8> = DynamicVolume::new(2, 1, 1, vec![100, 200]).unwrap();
+/// let kernel = StaticVolume::new(1, 1, 1, [2]).unwrap();
+///
+/// let output = convolve3d_saturating(&vol, &kernel);
+/// assert_eq!(output, DynamicVolume::new(2, 1, 1, vec![200, 255]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all slice, row and column values
+/// in the ranges `0..kernel.get_depth()`, `0..kernel.get_height()` and `0..kernel.get_width()`.
+#[cfg(feature = "std")]
+pub fn convolve3d_saturating<T, K, O>(
+    volume: &impl Volume<T>,
+    kernel: &impl Volume<K>,
+) -> DynamicVolume<O>
+where
+    T: SaturatingMul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: SaturatingAdd<Output = O> + Default + Clone + MaybeSend,
+{
+    let mut out = DynamicVolume::new(
+        volume.get_width(),
+        volume.get_height(),
+        volume.get_depth(),
+        vec![O::default(); volume.get_data().len()],
+    )
+    .unwrap();
+    write_convolution3d_saturating(volume, kernel, &mut out);
+    out
+}
+
+/// Write the 3D convolution of the provided volume and kernel into the specified buffer, without
+/// integer overflow.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution3d_saturating, StaticVolume};
+/// let vol: StaticVolume<u8, 2> = StaticVolume::new(1, 1, 2, [100, 200]).unwrap();
+/// let kernel = StaticVolume::new(1, 1, 1, [2]).unwrap();
+///
+/// let mut output = StaticVolume::new(1, 1, 2, [0; 2]).unwrap();
+/// write_convolution3d_saturating(&vol, &kernel, &mut output);
+/// assert_eq!(output, StaticVolume::new(1, 1, 2, [200, 255]).unwrap());
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all slice, row and column values
+/// in the ranges `0..kernel.get_depth()`, `0..kernel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution3d_saturating<T, K, O>(
+    volume: &impl Volume<T>,
+    kernel: &impl Volume<K>,
+    out: &mut impl VolumeMut<O>,
+) where
+    T: SaturatingMul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: SaturatingAdd<Output = O> + Clone + MaybeSend,
+{
+    for (alignment, value) in taps_3d(kernel, volume.get_width(), volume.get_height()) {
+        // Apply this kernel value and add to the buffer
+        update_buffer_saturating(volume.get_data(), value, alignment, out.get_data_mut());
+    }
+}
+
+/// Iterate over the values of the kernel, along with the alignment at which each value
+/// should be applied to a volume of the specified width and height.
+///
+/// The kernel is anchored at its center, in the same way as the two dimensional convolutions.
+fn taps_3d<K: Clone>(
+    kernel: &impl Volume<K>,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (isize, K)> + '_ {
+    let anchor = |size: usize| (((size + 1) >> 1).saturating_sub(1)) as isize;
+    let (anchor_slice, anchor_row, anchor_col) = (
+        anchor(kernel.get_depth()),
+        anchor(kernel.get_height()),
+        anchor(kernel.get_width()),
+    );
+
+    // Visit the kernel in reverse, as is the custom for convolutions
+    (0..kernel.get_depth()).rev().flat_map(move |slice| {
+        (0..kernel.get_height()).rev().flat_map(move |row| {
+            (0..kernel.get_width()).rev().map(move |col| {
+                // Determine the number of elements that the volume needs to be shifted
+                let slices_off = anchor_slice - slice as isize;
+                let rows_off = anchor_row - row as isize;
+                let cols_off = anchor_col - col as isize;
+                let alignment =
+                    (slices_off * height as isize + rows_off) * width as isize + cols_off;
+                (
+                    alignment,
+                    kernel.get_value(slice, row, col).unwrap().clone(),
+                )
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{write_convolution, write_convolution3d, StaticMatrix, StaticVolume};
+
+    #[test]
+    fn single_slice_matches_write_convolution() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let kernel_data = [1, -2, 3, 4, 0, 6, -7, 8, 9];
+
+        let mut expected = StaticMatrix::new(4, 3, [0; 12]).unwrap();
+        write_convolution(
+            &StaticMatrix::new(4, 3, data).unwrap(),
+            &StaticMatrix::new(3, 3, kernel_data).unwrap(),
+            &mut expected,
+        );
+
+        let mut output = StaticVolume::new(4, 3, 1, [0; 12]).unwrap();
+        write_convolution3d(
+            &StaticVolume::new(4, 3, 1, data).unwrap(),
+            &StaticVolume::new(3, 3, 1, kernel_data).unwrap(),
+            &mut output,
+        );
+
+        assert_eq!(output.into_parts().3, expected.into_parts().2);
+    }
+
+    #[test]
+    fn impulse_response() {
+        let mut data = [0; 27];
+        data[13] = 1;
+        let kernel_data: [i32; 27] = core::array::from_fn(|i| i as i32 + 1);
+
+        let mut output = StaticVolume::new(3, 3, 3, [0; 27]).unwrap();
+        write_convolution3d(
+            &StaticVolume::new(3, 3, 3, data).unwrap(),
+            &StaticVolume::new(3, 3, 3, kernel_data).unwrap(),
+            &mut output,
+        );
+
+        // Like the 2D convolution, an impulse produces the kernel reversed
+        let mut expected = kernel_data;
+        expected.reverse();
+        assert_eq!(output.into_parts().3, expected);
+    }
+}
@@ -0,0 +1,271 @@
+//! Corner detection with the Harris and Shi–Tomasi detectors.
+
+use crate::edges::{channel_gradients, channels};
+use crate::sharpen::gaussian_blur;
+use crate::{max_filter, Channels, DynamicMatrix, GradientOperator, Matrix, MaybeSync};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// The measure of how much each pixel of an image looks like a corner.
+///
+/// Both measures are computed from the structure tensor of the image, which sums the products of
+/// its gradients over a Gaussian window. Its two eigenvalues are both large at a corner, where the
+/// image changes in every direction, while along an edge only one of them is large.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CornerResponse {
+    /// The Harris response, `det - k * trace²`, with the sensitivity `k` usually between 0.04 and
+    /// 0.06. This is negative along edges, and close to zero in flat areas.
+    Harris(f64),
+    /// The Shi–Tomasi response, which is the smaller eigenvalue of the structure tensor
+    ShiTomasi,
+}
+
+/// A corner found by [`corners`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Corner {
+    /// The row of the pixel containing the corner
+    pub row: usize,
+    /// The column of the pixel containing the corner
+    pub col: usize,
+    /// The value of the [`CornerResponse`] at the corner
+    pub score: f64,
+}
+
+/// Compute how much each pixel of the specified image looks like a corner.
+///
+/// The gradient of the image is computed with the [`GradientOperator::Sobel`] kernels, and the
+/// products of its components are averaged with a Gaussian of standard deviation `sigma` to give
+/// the structure tensor at each pixel, from which the [`CornerResponse`] is computed. The
+/// structure tensors of each of the pixel's [`Channels`] are added together.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{corner_response, CornerResponse, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(16, 16, (0..256).map(|i| {
+///     if i / 16 >= 8 && i % 16 >= 8 { 1.0 } else { 0.0 }
+/// }).collect()).unwrap();
+///
+/// let response = corner_response(&image, 1.0, CornerResponse::Harris(0.04));
+/// let score = |row: usize, col: usize| response.get_data()[row * 16 + col];
+/// assert!(score(8, 8) > 0.0); // At the corner of the bright square
+/// assert!(score(13, 8) < 0.0); // Along its edge
+/// assert!(score(0, 0).abs() < 1e-6); // In the flat background
+/// ```
+///
+/// # Panics
+/// If `sigma` is not positive.
+pub fn corner_response<T>(
+    image: &impl Matrix<T>,
+    sigma: f64,
+    response: CornerResponse,
+) -> DynamicMatrix<f64>
+where
+    T: Channels + MaybeSync,
+{
+    assert!(sigma > 0.0, "Sigma must be positive");
+    let (width, height) = (image.get_width(), image.get_height());
+
+    // The `xx`, `xy`, and `yy` products of the gradient, summed over every channel
+    let mut products = [(); 3].map(|_| vec![0.0f64; width * height]);
+    for channel in channels(image) {
+        let gradients = channel_gradients(channel, width, height, GradientOperator::Sobel);
+        for (i, (x, y)) in gradients.get_data().iter().enumerate() {
+            products[0][i] += x * x;
+            products[1][i] += x * y;
+            products[2][i] += y * y;
+        }
+    }
+    let [xx, xy, yy] = products.map(|product| gaussian_blur(&product, width, sigma));
+
+    #[cfg(not(feature = "rayon"))]
+    let pixels = 0..xx.len();
+    #[cfg(feature = "rayon")]
+    let pixels = (0..xx.len()).into_par_iter();
+
+    let scores = pixels
+        .map(|i| {
+            let (a, b, c) = (xx[i], xy[i], yy[i]);
+            match response {
+                CornerResponse::Harris(k) => a * c - b * b - k * (a + c) * (a + c),
+                CornerResponse::ShiTomasi => (a + c) / 2.0 - ((a - c) / 2.0).hypot(b),
+            }
+        })
+        .collect();
+    DynamicMatrix::new(width, height, scores).unwrap()
+}
+
+/// Find the strongest corners in the specified image.
+///
+/// The [`corner_response`] of the image is computed, and each pixel with a positive response
+/// which is the largest within `radius` pixels horizontally and vertically is a candidate. Starting
+/// from the strongest, candidates are then accepted unless they lie within `radius` of a corner
+/// which has already been accepted, which removes neighbouring pixels with exactly the same
+/// response. The `count` strongest corners are returned, strongest first.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{corners, CornerResponse, DynamicMatrix};
+/// let image = DynamicMatrix::new(16, 16, (0..256).map(|i| {
+///     let (row, col) = (i / 16, i % 16);
+///     if (4..12).contains(&row) && (4..12).contains(&col) { 255u8 } else { 0 }
+/// }).collect()).unwrap();
+///
+/// let found = corners(&image, 1.0, CornerResponse::ShiTomasi, 2, 10);
+/// let positions: Vec<_> = found.iter().map(|c| (c.row, c.col)).collect();
+/// assert_eq!(positions, [(4, 4), (4, 11), (11, 4), (11, 11)]);
+/// ```
+///
+/// # Panics
+/// If `sigma` is not positive.
+pub fn corners<T>(
+    image: &impl Matrix<T>,
+    sigma: f64,
+    response: CornerResponse,
+    radius: usize,
+    count: usize,
+) -> Vec<Corner>
+where
+    T: Channels + MaybeSync,
+{
+    let scores = corner_response(image, sigma, response);
+    let maxima = max_filter(&scores, radius, radius);
+    let width = scores.get_width();
+
+    let mut candidates: Vec<Corner> = scores
+        .get_data()
+        .iter()
+        .zip(maxima.get_data())
+        .enumerate()
+        .filter(|(_, (score, max))| **score > 0.0 && score == max)
+        .map(|(i, (score, _))| Corner {
+            row: i / width,
+            col: i % width,
+            score: *score,
+        })
+        .collect();
+    // The sort is stable, so corners with the same score stay in row-major order
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut corners: Vec<Corner> = Vec::with_capacity(count.min(candidates.len()));
+    for candidate in candidates {
+        if corners.len() == count {
+            break;
+        }
+        let taken = corners.iter().any(|corner| {
+            corner.row.abs_diff(candidate.row) <= radius
+                && corner.col.abs_diff(candidate.col) <= radius
+        });
+        if !taken {
+            corners.push(candidate);
+        }
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{corner_response, corners, Corner, CornerResponse};
+    use crate::{DynamicMatrix, Matrix, MatrixMut, SubPixels};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    /// An image with a bright rectangle covering the specified rows and columns
+    fn rectangle(
+        width: usize,
+        height: usize,
+        rows: core::ops::Range<usize>,
+        cols: core::ops::Range<usize>,
+    ) -> DynamicMatrix<f64> {
+        let data = (0..width * height)
+            .map(|i| {
+                if rows.contains(&(i / width)) && cols.contains(&(i % width)) {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        DynamicMatrix::new(width, height, data).unwrap()
+    }
+
+    fn near(corner: &Corner, row: usize, col: usize) -> bool {
+        corner.row.abs_diff(row) <= 1 && corner.col.abs_diff(col) <= 1
+    }
+
+    #[test_case(CornerResponse::Harris(0.04); "harris")]
+    #[test_case(CornerResponse::ShiTomasi; "shi tomasi")]
+    fn rectangle_corners(response: CornerResponse) {
+        let image = rectangle(20, 16, 4..11, 5..15);
+        let found = corners(&image, 1.5, response, 3, 100);
+        assert_eq!(found.len(), 4);
+        for (row, col) in [(4, 5), (4, 14), (10, 5), (10, 14)] {
+            let matches = found.iter().filter(|c| near(c, row, col)).count();
+            assert_eq!(matches, 1, "No corner found near ({row}, {col})");
+        }
+    }
+
+    #[test]
+    fn strongest_first() {
+        // A bright and a faint square, each with four corners
+        let mut image = rectangle(30, 12, 3..9, 3..9);
+        let faint = rectangle(30, 12, 3..9, 18..24);
+        for (x, y) in image.get_data_mut().iter_mut().zip(faint.get_data()) {
+            *x += 0.5 * y;
+        }
+        let found = corners(&image, 1.0, CornerResponse::ShiTomasi, 2, 4);
+        assert_eq!(found.len(), 4);
+        assert!(found.iter().all(|c| c.col < 14));
+        assert!(found.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn radius_merges_nearby_corners() {
+        let image = rectangle(20, 20, 6..14, 6..14);
+        assert_eq!(
+            corners(&image, 0.8, CornerResponse::ShiTomasi, 1, 100).len(),
+            4
+        );
+        assert_eq!(
+            corners(&image, 0.8, CornerResponse::ShiTomasi, 8, 100).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn edges_are_not_corners() {
+        let image = rectangle(12, 12, 0..12, 6..12);
+        let harris = corner_response(&image, 1.0, CornerResponse::Harris(0.05));
+        assert!(harris.get_data().iter().all(|&x| x <= 1e-9));
+        assert!(corners(&image, 1.0, CornerResponse::Harris(0.05), 2, 10).is_empty());
+    }
+
+    #[test]
+    fn channels_are_combined() {
+        // The rectangle's horizontal edges are in one channel, and its vertical edges in another
+        let rows = rectangle(16, 16, 5..16, 0..16);
+        let cols = rectangle(16, 16, 0..16, 5..16);
+        let data = rows
+            .get_data()
+            .iter()
+            .zip(cols.get_data())
+            .map(|(r, c)| SubPixels([*r, *c]))
+            .collect();
+        let image = DynamicMatrix::new(16, 16, data).unwrap();
+        let found = corners(&image, 1.0, CornerResponse::ShiTomasi, 3, 10);
+        assert_eq!(found.len(), 1);
+        assert!(near(&found[0], 5, 5));
+    }
+
+    #[test]
+    fn empty_image() {
+        let image = DynamicMatrix::<u8>::new(0, 0, vec![]).unwrap();
+        assert!(corners(&image, 1.0, CornerResponse::ShiTomasi, 1, 10).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+//! Deconvolution, which undoes the blur of a known kernel.
+
+use crate::fft::Fft2d;
+use crate::kernel::{self, Tap};
+use crate::{convolve2d_sparse, Channels, Complex, DynamicMatrix, Matrix, MaybeSend, MaybeSync};
+use core::ops::{Add, Mul};
+use std::prelude::v1::*;
+use std::vec;
+
+/// Blurred values smaller than this are treated as zero by [`richardson_lucy`], to avoid dividing
+/// by zero.
+const RATIO_EPSILON: f64 = 1e-12;
+
+/// Undo the blur of a known point spread function using Richardson–Lucy deconvolution.
+///
+/// Starting from the blurred image, each iteration blurs the current estimate with the `psf`,
+/// compares the result with the blurred image, and corrects the estimate by the ratio between the
+/// two. The estimate converges towards the most likely unblurred image under Poisson noise, and
+/// stays non-negative as long as the image and the `psf` are. More iterations restore more detail,
+/// but also amplify more noise.
+///
+/// The `psf` is applied with [`convolve2d`](crate::convolve2d), and the correction with the
+/// corresponding correlation, so the `psf` should be positioned in the same way as a kernel, and
+/// should sum to one. Multi-channel images are deconvolved one channel at a time.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, kernel, richardson_lucy, DynamicMatrix, Matrix};
+/// let mut data = vec![0.0; 15 * 15];
+/// data[7 * 15 + 7] = 1.0;
+/// let image = DynamicMatrix::new(15, 15, data).unwrap();
+/// let psf = kernel::gaussian(5, 1.0);
+///
+/// let blurred: DynamicMatrix<f64> = convolve2d(&image, &psf);
+/// let restored = richardson_lucy(&blurred, &psf, 100);
+/// assert!(restored.get_data()[7 * 15 + 7] > 4.0 * blurred.get_data()[7 * 15 + 7]);
+/// ```
+pub fn richardson_lucy<T>(
+    image: &impl Matrix<T>,
+    psf: &impl Matrix<f64>,
+    iterations: usize,
+) -> DynamicMatrix<T>
+where
+    T: Channels + Mul<f64, Output = T> + Add<Output = T> + Default + Clone + MaybeSend + MaybeSync,
+{
+    let taps = kernel::sparse(psf);
+    // Negating the offsets of the taps gives the correlation with the psf, which is the adjoint of
+    // the convolution, including at the edges of the image
+    let adjoint: Vec<_> = taps
+        .iter()
+        .map(|tap| Tap::new(-tap.row, -tap.col, tap.weight))
+        .collect();
+
+    let (width, height) = (image.get_width(), image.get_height());
+
+    // Near the edges of the image, part of the psf falls outside of it, so the corrections are
+    // normalized by how much of the psf falls inside
+    let ones = (0..width * height)
+        .map(|_| T::from_channels(|_| 1.0))
+        .collect();
+    let ones = DynamicMatrix::new(width, height, ones).unwrap();
+    let normalization: DynamicMatrix<T> = convolve2d_sparse(&ones, &adjoint);
+
+    let mut estimate = DynamicMatrix::new(width, height, image.get_data().to_vec()).unwrap();
+    for _ in 0..iterations {
+        let blurred: DynamicMatrix<T> = convolve2d_sparse(&estimate, &taps);
+        let ratio = image
+            .get_data()
+            .iter()
+            .zip(blurred.get_data())
+            .map(|(x, b)| {
+                T::from_channels(|k| match b.channel(k) {
+                    b if b > RATIO_EPSILON => x.channel(k) / b,
+                    _ => 0.0,
+                })
+            })
+            .collect();
+        let ratio = DynamicMatrix::new(width, height, ratio).unwrap();
+
+        let correction: DynamicMatrix<T> = convolve2d_sparse(&ratio, &adjoint);
+        let data = estimate
+            .get_data()
+            .iter()
+            .zip(correction.get_data())
+            .zip(normalization.get_data())
+            .map(|((e, c), n)| {
+                T::from_channels(|k| match n.channel(k) {
+                    n if n > RATIO_EPSILON => e.channel(k) * c.channel(k) / n,
+                    _ => e.channel(k),
+                })
+            })
+            .collect();
+        estimate = DynamicMatrix::new(width, height, data).unwrap();
+    }
+    estimate
+}
+
+/// Undo the blur of a known point spread function using Wiener deconvolution.
+///
+/// The image is divided by the `psf` in the frequency domain, using a fast Fourier transform.
+/// Frequencies which the `psf` all but removes would be amplified enormously by a plain division,
+/// so `noise_to_signal` gives the ratio of the power of the noise to the power of the signal,
+/// which limits how much each frequency is amplified. Larger values give smoother results, and a
+/// value of zero performs a plain division.
+///
+/// The `psf` is positioned in the same way as a kernel in [`convolve2d`](crate::convolve2d). The
+/// image is extended by repeating its edge pixels up to a power of two size before it is
+/// transformed, which reduces ringing at its edges. Multi-channel images are deconvolved one
+/// channel at a time.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, kernel, wiener_deconvolution, DynamicMatrix, Matrix};
+/// let data = (0..16 * 16).map(|i| if (i / 16) % 8 < 4 { 0.0 } else { 1.0 }).collect();
+/// let image = DynamicMatrix::new(16, 16, data).unwrap();
+/// let psf = kernel::box_blur(3);
+///
+/// let blurred: DynamicMatrix<f64> = convolve2d(&image, &psf);
+/// let restored: DynamicMatrix<f64> = wiener_deconvolution(&blurred, &psf, 1e-3);
+/// let error = |x: &DynamicMatrix<f64>| (x.get_data()[5 * 16 + 8] - 0.0).abs();
+/// assert!(error(&restored) < error(&blurred));
+/// ```
+pub fn wiener_deconvolution<T>(
+    image: &impl Matrix<T>,
+    psf: &impl Matrix<f64>,
+    noise_to_signal: f64,
+) -> DynamicMatrix<T>
+where
+    T: Channels,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    if width == 0 || height == 0 {
+        let data = image
+            .get_data()
+            .iter()
+            .map(|x| T::from_channels(|k| x.channel(k)));
+        return DynamicMatrix::new(width, height, data.collect()).unwrap();
+    }
+
+    // Leave room for at least the kernel's width of padding around the image
+    let padded_width = (width + psf.get_width()).next_power_of_two();
+    let padded_height = (height + psf.get_height()).next_power_of_two();
+    let fft = Fft2d::new(padded_width, padded_height);
+
+    // Place the psf so that the convolution is a circular convolution with the padded image
+    let mut transfer = vec![Complex::default(); padded_width * padded_height];
+    for tap in kernel::sparse(psf) {
+        let row = (-tap.row).rem_euclid(padded_height as isize) as usize;
+        let col = (-tap.col).rem_euclid(padded_width as isize) as usize;
+        transfer[row * padded_width + col] =
+            transfer[row * padded_width + col] + Complex::new(tap.weight, 0.0);
+    }
+    fft.forward(&mut transfer);
+    let filter: Vec<_> = transfer
+        .iter()
+        .map(|h| {
+            let power = h.re * h.re + h.im * h.im;
+            match power + noise_to_signal {
+                denominator if denominator > 0.0 => h.conj() / denominator,
+                _ => Complex::default(),
+            }
+        })
+        .collect();
+
+    let data = image.get_data();
+    let channels: Vec<Vec<f64>> = (0..T::CHANNELS)
+        .map(|k| {
+            let mut spectrum: Vec<_> = (0..padded_width * padded_height)
+                .map(|i| {
+                    let row = extend(i / padded_width, height, padded_height);
+                    let col = extend(i % padded_width, width, padded_width);
+                    Complex::new(data[row * width + col].channel(k), 0.0)
+                })
+                .collect();
+            fft.forward(&mut spectrum);
+            spectrum
+                .iter_mut()
+                .zip(&filter)
+                .for_each(|(x, f)| *x = *x * *f);
+            fft.inverse(&mut spectrum);
+            (0..width * height)
+                .map(|i| spectrum[(i / width) * padded_width + i % width].re)
+                .collect()
+        })
+        .collect();
+
+    let data = (0..width * height)
+        .map(|i| T::from_channels(|k| channels[k][i]))
+        .collect();
+    DynamicMatrix::new(width, height, data).unwrap()
+}
+
+/// Find the index along an axis of `len` pixels to read for position `index` of the padded axis of
+/// `padded_len` pixels.
+///
+/// The padding repeats the last pixel for the first half, and the first pixel for the second half,
+/// so that the padded image wraps around smoothly.
+fn extend(index: usize, len: usize, padded_len: usize) -> usize {
+    if index < len {
+        index
+    } else if index - (len - 1) <= padded_len - index {
+        len - 1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{richardson_lucy, wiener_deconvolution};
+    use crate::{convolve2d, kernel, DynamicMatrix, Matrix, SubPixels};
+    use std::prelude::v1::*;
+
+    /// A smooth blob, surrounded by a flat background
+    fn blob() -> DynamicMatrix<f64> {
+        let data = (0..24 * 20)
+            .map(|i| {
+                let (r, c) = ((i / 24) as f64 - 10.0, (i % 24) as f64 - 12.0);
+                0.1 + f64::exp(-(r * r + c * c) / 8.0)
+            })
+            .collect();
+        DynamicMatrix::new(24, 20, data).unwrap()
+    }
+
+    fn max_error(x: &DynamicMatrix<f64>, y: &DynamicMatrix<f64>) -> f64 {
+        x.get_data()
+            .iter()
+            .zip(y.get_data())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// The maximum error away from the edges of the image, where the blur sees only background
+    fn interior_error(x: &DynamicMatrix<f64>, y: &DynamicMatrix<f64>) -> f64 {
+        let mut error: f64 = 0.0;
+        for row in 4..16 {
+            for col in 4..20 {
+                let (a, b) = (
+                    x.get_value(row, col).unwrap(),
+                    y.get_value(row, col).unwrap(),
+                );
+                error = error.max((a - b).abs());
+            }
+        }
+        error
+    }
+
+    #[test]
+    fn wiener_recovers_blob() {
+        let psf = kernel::gaussian(5, 1.0);
+        let blurred: DynamicMatrix<f64> = convolve2d(&blob(), &psf);
+        let restored: DynamicMatrix<f64> = wiener_deconvolution(&blurred, &psf, 1e-6);
+
+        let before = interior_error(&blurred, &blob());
+        let after = interior_error(&restored, &blob());
+        assert!(after < before / 10.0, "{} {}", before, after);
+    }
+
+    #[test]
+    fn wiener_identity_psf() {
+        let psf = DynamicMatrix::new(1, 1, vec![1.0]).unwrap();
+        let restored: DynamicMatrix<f64> = wiener_deconvolution(&blob(), &psf, 0.0);
+        assert!(max_error(&restored, &blob()) < 1e-9);
+    }
+
+    #[test]
+    fn richardson_lucy_improves_over_iterations() {
+        let psf = kernel::gaussian(5, 1.0);
+        let blurred: DynamicMatrix<f64> = convolve2d(&blob(), &psf);
+
+        let few = richardson_lucy(&blurred, &psf, 5);
+        let many = richardson_lucy(&blurred, &psf, 50);
+        let before = interior_error(&blurred, &blob());
+        assert!(interior_error(&few, &blob()) < before);
+        assert!(interior_error(&many, &blob()) < before);
+
+        // Blurring the estimate again matches the blurred image more closely with each iteration
+        let residual = |x: &DynamicMatrix<f64>| max_error(&convolve2d(x, &psf), &blurred);
+        assert!(
+            residual(&many) < residual(&few),
+            "{} {}",
+            residual(&many),
+            residual(&few)
+        );
+    }
+
+    #[test]
+    fn subpixels_per_channel() {
+        let psf = kernel::box_blur(3);
+        let blurred: DynamicMatrix<f64> = convolve2d(&blob(), &psf);
+        let image = DynamicMatrix::new(
+            24,
+            20,
+            blurred
+                .get_data()
+                .iter()
+                .map(|x| SubPixels([*x, 2.0 * x]))
+                .collect(),
+        )
+        .unwrap();
+
+        let expected = richardson_lucy(&blurred, &psf, 10);
+        let output = richardson_lucy(&image, &psf, 10);
+        for (x, y) in output.get_data().iter().zip(expected.get_data()) {
+            assert!((x.0[0] - y).abs() < 1e-9 && (x.0[1] - 2.0 * y).abs() < 1e-9);
+        }
+
+        let expected: DynamicMatrix<f64> = wiener_deconvolution(&blurred, &psf, 1e-3);
+        let output: DynamicMatrix<SubPixels<f64, 2>> = wiener_deconvolution(&image, &psf, 1e-3);
+        for (x, y) in output.get_data().iter().zip(expected.get_data()) {
+            assert!((x.0[0] - y).abs() < 1e-9 && (x.0[1] - 2.0 * y).abs() < 1e-9);
+        }
+    }
+}
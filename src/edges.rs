@@ -0,0 +1,422 @@
+//! Image gradients and edge detection.
+
+use crate::sharpen::gaussian_blur;
+use crate::{kernel, neighborhood_map, Border, Channels, DynamicMatrix, Matrix, MaybeSync};
+use core::f64::consts::PI;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// The pair of kernels used to estimate the gradient of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GradientOperator {
+    /// The [`kernel::sobel`] filters
+    #[default]
+    Sobel,
+    /// The [`kernel::scharr`] filters, which estimate the orientation of diagonal edges more
+    /// accurately
+    Scharr,
+}
+
+impl GradientOperator {
+    /// Get the data of the kernels for the `x` and `y` directions.
+    fn kernels(&self) -> ([f64; 9], [f64; 9]) {
+        let (x, y) = match self {
+            GradientOperator::Sobel => (kernel::sobel::x(), kernel::sobel::y()),
+            GradientOperator::Scharr => (kernel::scharr::x(), kernel::scharr::y()),
+        };
+        (x.into_parts().2, y.into_parts().2)
+    }
+}
+
+/// Compute the magnitude and orientation of the gradient of the specified image.
+///
+/// The gradient is estimated with the kernels of the specified [`GradientOperator`], replicating
+/// the pixels at the edges of the image. For pixels with several [`Channels`], the gradient of the
+/// channel with the largest magnitude is used.
+///
+/// The returned matrices hold the `(magnitude, orientation)` of the gradient at each pixel. The
+/// orientation is the angle of the direction in which the image gets brighter, in radians from
+/// the direction of increasing columns towards the direction of increasing rows, in the range
+/// `-π..=π`.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{gradient, DynamicMatrix, GradientOperator, Matrix};
+/// let image = DynamicMatrix::new(3, 3, vec![
+///     0.0, 0.0, 0.0,
+///     0.0, 0.0, 0.0,
+///     1.0, 1.0, 1.0,
+/// ]).unwrap();
+///
+/// let (magnitude, orientation) = gradient(&image, GradientOperator::Sobel);
+/// assert_eq!(magnitude.get_data()[4], 4.0);
+/// assert_eq!(orientation.get_data()[4], std::f64::consts::FRAC_PI_2);
+/// ```
+pub fn gradient<T>(
+    image: &impl Matrix<T>,
+    operator: GradientOperator,
+) -> (DynamicMatrix<f64>, DynamicMatrix<f64>)
+where
+    T: Channels + MaybeSync,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    let gradients = strongest_gradients(channels(image), width, height, operator);
+    let magnitude = gradients.iter().map(|(x, y)| x.hypot(*y)).collect();
+    let orientation = gradients.iter().map(|(x, y)| y.atan2(*x)).collect();
+    (
+        DynamicMatrix::new(width, height, magnitude).unwrap(),
+        DynamicMatrix::new(width, height, orientation).unwrap(),
+    )
+}
+
+/// Find the edges in the specified image with the Canny edge detector.
+///
+/// The image is first smoothed with a Gaussian of standard deviation `sigma` to suppress noise,
+/// unless `sigma` is zero. Its [`gradient`] is then computed with the [`GradientOperator::Sobel`]
+/// kernels, and thinned to lines one pixel wide by keeping only the pixels which are larger than
+/// their neighbours across the edge. Finally, pixels with a gradient magnitude of at least
+/// `high_threshold` are marked as edges, along with any pixels connected to them with a magnitude
+/// of at least `low_threshold`.
+///
+/// The thresholds are in the units of the gradient magnitude, which for the sobel kernels is four
+/// times the height of a sharp step. So, for a `u8` image, the magnitude ranges up to 1020.
+///
+/// The result is `true` for each pixel on an edge. It can be converted to a `GrayImage` by mapping
+/// each pixel to a `SubPixels<u8, 1>`, as shown below.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{canny, DynamicMatrix, Matrix, SubPixels};
+/// let image = DynamicMatrix::new(6, 1, vec![10u8, 10, 10, 200, 200, 200]).unwrap();
+///
+/// let edges = canny(&image, 0.0, 100.0, 200.0);
+/// assert_eq!(edges.get_data(), &[false, false, false, true, false, false]);
+///
+/// // Edge maps can be drawn in black and white
+/// let drawable = edges.map(|edge| SubPixels([if edge { 255u8 } else { 0 }]));
+/// ```
+///
+/// # Panics
+/// If `sigma` is negative, or `low_threshold` is larger than `high_threshold`.
+pub fn canny<T>(
+    image: &impl Matrix<T>,
+    sigma: f64,
+    low_threshold: f64,
+    high_threshold: f64,
+) -> DynamicMatrix<bool>
+where
+    T: Channels + MaybeSync,
+{
+    assert!(sigma >= 0.0, "Sigma must not be negative");
+    assert!(
+        low_threshold <= high_threshold,
+        "The low threshold must not exceed the high threshold"
+    );
+    let (width, height) = (image.get_width(), image.get_height());
+    let mut channels = channels(image);
+    if sigma > 0.0 {
+        for channel in &mut channels {
+            *channel = gaussian_blur(channel, width, sigma);
+        }
+    }
+    let gradients = strongest_gradients(channels, width, height, GradientOperator::Sobel);
+    let thin = suppress_non_maxima(&gradients, width, height);
+    let edges = hysteresis(&thin, width, height, low_threshold, high_threshold);
+    DynamicMatrix::new(width, height, edges).unwrap()
+}
+
+/// Split the specified image into a buffer of values for each of its channels.
+pub(crate) fn channels<T: Channels>(image: &impl Matrix<T>) -> Vec<Vec<f64>> {
+    (0..T::CHANNELS)
+        .map(|index| image.get_data().iter().map(|x| x.channel(index)).collect())
+        .collect()
+}
+
+/// Find the `(x, y)` gradient of each value in a single channel of an image, replicating its
+/// edges. The `y` gradient is positive when the image gets brighter in the direction of increasing
+/// rows.
+pub(crate) fn channel_gradients(
+    channel: Vec<f64>,
+    width: usize,
+    height: usize,
+    operator: GradientOperator,
+) -> DynamicMatrix<(f64, f64)> {
+    let (x_kernel, y_kernel) = operator.kernels();
+    let image = DynamicMatrix::new(width, height, channel).unwrap();
+    neighborhood_map(&image, (3, 3), Border::Replicate, |window| {
+        // The `y` kernels are positive towards the top of the image, so they are negated
+        window
+            .iter()
+            .zip(x_kernel.iter().zip(&y_kernel))
+            .fold((0.0f64, 0.0f64), |(x, y), (value, (x_weight, y_weight))| {
+                (x + value * x_weight, y - value * y_weight)
+            })
+    })
+}
+
+/// Find the `(x, y)` gradient of each pixel, using the channel with the largest magnitude.
+fn strongest_gradients(
+    channels: Vec<Vec<f64>>,
+    width: usize,
+    height: usize,
+    operator: GradientOperator,
+) -> Vec<(f64, f64)> {
+    let mut strongest = vec![(0.0f64, 0.0f64); width * height];
+    for channel in channels {
+        let gradients = channel_gradients(channel, width, height, operator);
+        for (best, (x, y)) in strongest.iter_mut().zip(gradients.get_data()) {
+            if x.hypot(*y) > best.0.hypot(best.1) {
+                *best = (*x, *y);
+            }
+        }
+    }
+    strongest
+}
+
+/// Get the `(row, col)` step to the neighbouring pixel which lies closest to the direction of the
+/// specified gradient.
+fn step((x, y): (f64, f64)) -> (isize, isize) {
+    let angle = y.atan2(x).rem_euclid(PI);
+    if !(PI / 8.0..7.0 * PI / 8.0).contains(&angle) {
+        (0, 1)
+    } else if angle < 3.0 * PI / 8.0 {
+        (1, 1)
+    } else if angle < 5.0 * PI / 8.0 {
+        (1, 0)
+    } else {
+        (1, -1)
+    }
+}
+
+/// Get the magnitude of each gradient which is a maximum along its own direction, or zero for
+/// those which are not.
+fn suppress_non_maxima(gradients: &[(f64, f64)], width: usize, height: usize) -> Vec<f64> {
+    let magnitude: Vec<f64> = gradients.iter().map(|(x, y)| x.hypot(*y)).collect();
+    let at = |row: isize, col: isize| {
+        if (0..height as isize).contains(&row) && (0..width as isize).contains(&col) {
+            magnitude[row as usize * width + col as usize]
+        } else {
+            0.0
+        }
+    };
+
+    #[cfg(not(feature = "rayon"))]
+    let pixels = 0..gradients.len();
+    #[cfg(feature = "rayon")]
+    let pixels = (0..gradients.len()).into_par_iter();
+
+    pixels
+        .map(|i| {
+            let (row, col) = ((i / width) as isize, (i % width) as isize);
+            let (x, y) = gradients[i];
+            let (dr, dc) = step((x, y));
+            let ahead = at(row + dr, col + dc);
+            let behind = at(row - dr, col - dc);
+            // A step edge gives two pixels with the same magnitude, so ties are broken towards
+            // the brighter side, which the gradient points to, to keep the line one pixel wide.
+            let (brighter, darker) = match dc as f64 * x + dr as f64 * y > 0.0 {
+                true => (ahead, behind),
+                false => (behind, ahead),
+            };
+            if magnitude[i] > brighter && magnitude[i] >= darker {
+                magnitude[i]
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Mark the pixels with a magnitude of at least `high` as edges, along with every pixel with a
+/// magnitude of at least `low` which is connected to them through its eight neighbours.
+fn hysteresis(magnitude: &[f64], width: usize, height: usize, low: f64, high: f64) -> Vec<bool> {
+    let mut edges = vec![false; magnitude.len()];
+    let mut stack: Vec<usize> = (0..magnitude.len())
+        .filter(|&i| magnitude[i] > 0.0 && magnitude[i] >= high)
+        .collect();
+    for &i in &stack {
+        edges[i] = true;
+    }
+
+    while let Some(i) = stack.pop() {
+        let (row, col) = (i / width, i % width);
+        for r in row.saturating_sub(1)..(row + 2).min(height) {
+            for c in col.saturating_sub(1)..(col + 2).min(width) {
+                let j = r * width + c;
+                if !edges[j] && magnitude[j] > 0.0 && magnitude[j] >= low {
+                    edges[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canny, gradient, step, GradientOperator};
+    use crate::{DynamicMatrix, Matrix, SubPixels};
+    use core::f64::consts::{FRAC_PI_4, PI};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    #[test_case(GradientOperator::Sobel, 8.0; "sobel")]
+    #[test_case(GradientOperator::Scharr, 32.0; "scharr")]
+    fn ramp(operator: GradientOperator, expected: f64) {
+        let image = DynamicMatrix::new(5, 4, (0..20).map(|i| (i % 5) as f64).collect()).unwrap();
+        let (magnitude, orientation) = gradient(&image, operator);
+        // Away from the left and right edges, the ramp has a constant gradient
+        for row in 0..4 {
+            for col in 1..4 {
+                assert_eq!(magnitude.get_data()[row * 5 + col], expected);
+                assert_eq!(orientation.get_data()[row * 5 + col], 0.0);
+            }
+        }
+    }
+
+    #[test_case((1.0, 0.0), (0, 1); "right")]
+    #[test_case((-1.0, 0.0), (0, 1); "left")]
+    #[test_case((1.0, 1.0), (1, 1); "down right")]
+    #[test_case((0.0, -1.0), (1, 0); "up")]
+    #[test_case((-1.0, 1.0), (1, -1); "down left")]
+    #[test_case(((PI / 3.0).cos(), (PI / 3.0).sin()), (1, 1); "sixty degrees")]
+    fn steps(gradient: (f64, f64), expected: (isize, isize)) {
+        assert_eq!(step(gradient), expected);
+    }
+
+    #[test]
+    fn diagonal_orientation() {
+        let image =
+            DynamicMatrix::new(4, 4, (0..16).map(|i| (i / 4 + i % 4) as f64).collect()).unwrap();
+        let (_, orientation) = gradient(&image, GradientOperator::Scharr);
+        assert!((orientation.get_data()[5] - FRAC_PI_4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn strongest_channel_wins() {
+        let image = DynamicMatrix::new(
+            3,
+            3,
+            vec![
+                SubPixels([0u8, 0]),
+                SubPixels([0, 0]),
+                SubPixels([0, 0]),
+                SubPixels([0, 0]),
+                SubPixels([0, 0]),
+                SubPixels([0, 0]),
+                SubPixels([10, 0]),
+                SubPixels([10, 0]),
+                SubPixels([0, 50]),
+            ],
+        )
+        .unwrap();
+        let (magnitude, orientation) = gradient(&image, GradientOperator::Sobel);
+        // The first channel gives a gradient of (-10, 30), and the second (50, 50)
+        assert!((magnitude.get_data()[4] - 50.0 * 2f64.sqrt()).abs() < 1e-12);
+        assert!((orientation.get_data()[4] - FRAC_PI_4).abs() < 1e-12);
+        assert_eq!(magnitude.get_data()[0], 0.0);
+    }
+
+    #[test]
+    fn square_outline() {
+        let data = (0..10 * 10)
+            .map(|i| {
+                let (row, col) = (i / 10, i % 10);
+                if (3..7).contains(&row) && (3..7).contains(&col) {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let image = DynamicMatrix::new(10, 10, data).unwrap();
+        let edges = canny(&image, 0.0, 1.0, 2.0);
+        let edges = edges.get_data();
+        // Each side of the square is found with a line one pixel wide
+        for row in 4..6 {
+            let line = &edges[row * 10..row * 10 + 10];
+            assert_eq!(line.iter().filter(|&&x| x).count(), 2);
+        }
+        for col in 4..6 {
+            assert_eq!((0..10).filter(|row| edges[row * 10 + col]).count(), 2);
+        }
+        assert!(!edges[0]);
+        assert!(!edges[5 * 10 + 5]);
+    }
+
+    #[test]
+    fn hysteresis_follows_weak_edges() {
+        // A vertical edge which fades towards the bottom of the image
+        let heights = [200u8, 200, 150, 100, 60, 40, 20, 10];
+        let data = (0..6 * heights.len())
+            .map(|i| if i % 6 >= 3 { heights[i / 6] } else { 0 })
+            .collect();
+        let image = DynamicMatrix::new(6, heights.len(), data).unwrap();
+
+        let strong = canny(&image, 0.0, 500.0, 500.0);
+        let linked = canny(&image, 0.0, 100.0, 500.0);
+        let rows_with_edges = |edges: &DynamicMatrix<bool>| {
+            edges
+                .get_data()
+                .chunks(6)
+                .filter(|row| row.iter().any(|&x| x))
+                .count()
+        };
+        assert!(rows_with_edges(&strong) < rows_with_edges(&linked));
+        assert!(rows_with_edges(&linked) < heights.len());
+        // Every strong edge is still an edge with the lower threshold
+        for (s, l) in strong.get_data().iter().zip(linked.get_data()) {
+            assert!(!s || *l);
+        }
+    }
+
+    #[test]
+    fn isolated_weak_edges_are_dropped() {
+        let image = DynamicMatrix::new(12, 3, {
+            let row = [0u8, 0, 0, 100, 100, 100, 100, 100, 100, 110, 110, 110];
+            row.iter().chain(&row).chain(&row).copied().collect()
+        })
+        .unwrap();
+        let edges = canny(&image, 0.0, 20.0, 200.0);
+        for row in edges.get_data().chunks(12) {
+            assert_eq!(row.iter().position(|&x| x), Some(3));
+            assert_eq!(row.iter().filter(|&&x| x).count(), 1);
+        }
+    }
+
+    #[test_case([10, 10, 10, 200, 200, 200], 3; "rising")]
+    #[test_case([200, 200, 200, 10, 10, 10], 2; "falling")]
+    fn ties_go_to_the_brighter_side(row: [u8; 6], expected: usize) {
+        let image = DynamicMatrix::new(6, 1, row.to_vec()).unwrap();
+        let edges = canny(&image, 0.0, 100.0, 200.0);
+        let marked: Vec<_> = (0..6).filter(|&i| edges.get_data()[i]).collect();
+        assert_eq!(marked, [expected]);
+    }
+
+    #[test]
+    fn smoothing_removes_noise() {
+        let data = (0..16 * 16)
+            .map(|i| if (i * 7919) % 13 == 0 { 30u8 } else { 0 })
+            .collect();
+        let image = DynamicMatrix::new(16, 16, data).unwrap();
+        let noisy = canny(&image, 0.0, 50.0, 100.0);
+        let smooth = canny(&image, 2.0, 50.0, 100.0);
+        assert!(noisy.get_data().iter().any(|&x| x));
+        assert!(!smooth.get_data().iter().any(|&x| x));
+    }
+
+    #[test]
+    #[should_panic]
+    fn thresholds_out_of_order() {
+        let image = DynamicMatrix::new(1, 1, vec![0.0]).unwrap();
+        canny(&image, 0.0, 2.0, 1.0);
+    }
+}
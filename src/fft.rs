@@ -0,0 +1,194 @@
+//! A minimal radix-2 fast Fourier transform, used to apply large kernels.
+
+use crate::Complex;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+
+/// The precomputed twiddle factors for transforms of a fixed, power of two length.
+#[derive(Debug, Clone)]
+pub(crate) struct Fft {
+    /// The `len / 2` roots of unity used by the forward transform
+    twiddles: Vec<Complex<f64>>,
+}
+
+impl Fft {
+    /// Prepare transforms of the specified length.
+    ///
+    /// # Panics
+    /// If `len` is not a power of two.
+    pub(crate) fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two(), "FFT length must be a power of two");
+        let step = -2.0 * core::f64::consts::PI / len as f64;
+        let twiddles = (0..len / 2)
+            .map(|k| Complex::from_polar(1.0, step * k as f64))
+            .collect();
+        Self { twiddles }
+    }
+
+    /// Get the length of the transforms
+    pub(crate) fn len(&self) -> usize {
+        (self.twiddles.len() * 2).max(1)
+    }
+
+    /// Replace `data` with its discrete Fourier transform.
+    pub(crate) fn forward(&self, data: &mut [Complex<f64>]) {
+        let len = self.len();
+        assert_eq!(data.len(), len, "Data length does not match the FFT");
+        if len < 2 {
+            return;
+        }
+
+        // Reorder the data so that the butterflies can be computed in place
+        let bits = len.trailing_zeros();
+        for i in 0..len {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut size = 2;
+        while size <= len {
+            let stride = len / size;
+            for start in (0..len).step_by(size) {
+                for k in 0..size / 2 {
+                    let t = self.twiddles[k * stride] * data[start + k + size / 2];
+                    let u = data[start + k];
+                    data[start + k] = u + t;
+                    data[start + k + size / 2] = u - t;
+                }
+            }
+            size *= 2;
+        }
+    }
+
+    /// Replace `data` with its inverse discrete Fourier transform.
+    pub(crate) fn inverse(&self, data: &mut [Complex<f64>]) {
+        // The inverse transform is the conjugate of the forward transform of the conjugate
+        data.iter_mut().for_each(|x| *x = x.conj());
+        self.forward(data);
+        let scale = 1.0 / self.len() as f64;
+        data.iter_mut().for_each(|x| *x = x.conj() * scale);
+    }
+}
+
+/// Transforms of two dimensional data with power of two dimensions, stored in row-major order.
+#[derive(Debug, Clone)]
+pub(crate) struct Fft2d {
+    /// The transform applied to each row
+    rows: Fft,
+    /// The transform applied to each column
+    columns: Fft,
+}
+
+impl Fft2d {
+    /// Prepare transforms of data with the specified dimensions.
+    ///
+    /// # Panics
+    /// If either dimension is not a power of two.
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            rows: Fft::new(width),
+            columns: Fft::new(height),
+        }
+    }
+
+    /// Replace `data` with its two dimensional discrete Fourier transform.
+    pub(crate) fn forward(&self, data: &mut [Complex<f64>]) {
+        self.apply(data, Fft::forward);
+    }
+
+    /// Replace `data` with its two dimensional inverse discrete Fourier transform.
+    pub(crate) fn inverse(&self, data: &mut [Complex<f64>]) {
+        self.apply(data, Fft::inverse);
+    }
+
+    /// Transform each row, and then each column, by transposing the data so that the columns can
+    /// be transformed as rows.
+    fn apply(&self, data: &mut [Complex<f64>], transform: fn(&Fft, &mut [Complex<f64>])) {
+        let (width, height) = (self.rows.len(), self.columns.len());
+        assert_eq!(
+            data.len(),
+            width * height,
+            "Data length does not match the FFT"
+        );
+
+        #[cfg(not(feature = "rayon"))]
+        data.chunks_mut(width)
+            .for_each(|row| transform(&self.rows, row));
+        #[cfg(feature = "rayon")]
+        data.par_chunks_mut(width)
+            .for_each(|row| transform(&self.rows, row));
+
+        let mut transposed = transpose(data, width);
+
+        #[cfg(not(feature = "rayon"))]
+        transposed
+            .chunks_mut(height)
+            .for_each(|column| transform(&self.columns, column));
+        #[cfg(feature = "rayon")]
+        transposed
+            .par_chunks_mut(height)
+            .for_each(|column| transform(&self.columns, column));
+
+        data.copy_from_slice(&transpose(&transposed, height));
+    }
+}
+
+/// Transpose a row-major buffer with rows of `width` values.
+fn transpose(data: &[Complex<f64>], width: usize) -> Vec<Complex<f64>> {
+    let height = data.len() / width;
+    (0..width)
+        .flat_map(|col| (0..height).map(move |row| data[row * width + col]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fft, Fft2d};
+    use crate::Complex;
+    use std::prelude::v1::*;
+
+    #[test]
+    fn impulse() {
+        let fft = Fft::new(8);
+        let mut data = vec![Complex::default(); 8];
+        data[0] = Complex::new(1.0, 0.0);
+        fft.forward(&mut data);
+        assert!(data.iter().all(|x| *x == Complex::new(1.0, 0.0)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let fft = Fft::new(16);
+        let original: Vec<_> = (0..16)
+            .map(|i| Complex::new(i as f64, (i * i) as f64 - 3.0))
+            .collect();
+
+        let mut data = original.clone();
+        fft.forward(&mut data);
+        fft.inverse(&mut data);
+
+        for (x, y) in data.iter().zip(&original) {
+            assert!((*x - *y).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn round_trip_2d() {
+        let fft = Fft2d::new(8, 4);
+        let original: Vec<_> = (0..32)
+            .map(|i| Complex::new((i * 7 % 5) as f64, i as f64))
+            .collect();
+
+        let mut data = original.clone();
+        fft.forward(&mut data);
+        assert!((data[0] - Complex::new(original.iter().map(|x| x.re).sum(), 496.0)).norm() < 1e-9);
+        fft.inverse(&mut data);
+
+        for (x, y) in data.iter().zip(&original) {
+            assert!((*x - *y).norm() < 1e-9);
+        }
+    }
+}
@@ -3,6 +3,456 @@
 //! The gaussian and box blur filters can be used to blur images while the sobel and laplacian 
 //! filters are commonly used for edge detection. See the documentation on each function for more
 //! detail.
+//!
+//! The [`Kernel`] type can be used to attach a divisor, bias, and anchor to any kernel matrix,
+//! in the style of the convolution matrices found in GIMP and OpenCV.
+
+use crate::{Matrix, Zero};
+use core::ops::Add;
+
+/// A kernel matrix, along with the divisor, bias, and anchor used to apply it.
+///
+/// Image editors like GIMP and libraries like OpenCV commonly describe kernels as an integer
+/// matrix with a divisor and an offset, such as `[1 2 1; 2 4 2; 1 2 1] / 16`. Wrapping a matrix
+/// in a `Kernel` allows such kernels to be expressed without going through floating point, so
+/// integer blurs can be computed exactly. Use [`convolve2d_kernel`](crate::convolve2d_kernel) or
+/// [`write_convolution_kernel`](crate::write_convolution_kernel) to apply a `Kernel` to an image.
+///
+/// Each output value is computed by summing the weighted neighborhood, dividing by the divisor
+/// (if any), then adding the bias (if any). Division follows the semantics of the output type's
+/// `Div` implementation, so integer division rounds towards zero.
+///
+/// The anchor is the `(row, col)` position of the kernel element that sits on top of the output
+/// pixel. By default, this is the center of the kernel, matching
+/// [`write_convolution`](crate::write_convolution).
+///
+/// # Example
+/// ```
+/// # use convolve2d::{Kernel, StaticMatrix};
+/// let kernel = Kernel::new(StaticMatrix::new(3, 3, [1, 2, 1, 2, 4, 2, 1, 2, 1]).unwrap())
+///     .with_divisor(16)
+///     .with_bias(1);
+///
+/// assert_eq!(kernel.divisor(), Some(&16));
+/// assert_eq!(kernel.bias(), Some(&1));
+/// assert_eq!(kernel.anchor(), (1, 1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kernel<K, M> {
+    /// The weights of the kernel
+    matrix: M,
+    /// The value that the weighted sum is divided by
+    divisor: Option<K>,
+    /// The value added to the result after division
+    bias: Option<K>,
+    /// The row and column of the kernel element placed over the output pixel
+    anchor: (usize, usize),
+}
+
+impl<K, M: Matrix<K>> Kernel<K, M> {
+    /// Create a new `Kernel` from the provided matrix, with no divisor or bias, anchored at the
+    /// center of the matrix.
+    ///
+    /// For matrices with an even width or height, the anchor is placed on the element just before
+    /// the center, which matches the behavior of [`write_convolution`](crate::write_convolution).
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{Kernel, StaticMatrix};
+    /// let kernel = Kernel::new(StaticMatrix::new(4, 2, [1; 8]).unwrap());
+    /// assert_eq!(kernel.anchor(), (0, 1));
+    /// ```
+    pub fn new(matrix: M) -> Self {
+        let anchor = default_anchor(&matrix);
+        Self {
+            matrix,
+            divisor: None,
+            bias: None,
+            anchor,
+        }
+    }
+
+    /// Set the value that the weighted sum will be divided by.
+    pub fn with_divisor(mut self, divisor: K) -> Self {
+        self.divisor = Some(divisor);
+        self
+    }
+
+    /// Set the value that will be added to each output after division.
+    pub fn with_bias(mut self, bias: K) -> Self {
+        self.bias = Some(bias);
+        self
+    }
+
+    /// Set the `(row, col)` position of the kernel element that sits on top of the output pixel.
+    ///
+    /// Returns `None` if the anchor lies outside of the kernel matrix.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{Kernel, StaticMatrix};
+    /// let kernel = Kernel::new(StaticMatrix::new(2, 2, [1, 1, 1, 1]).unwrap());
+    /// assert!(kernel.clone().with_anchor(1, 1).is_some());
+    /// assert!(kernel.with_anchor(2, 0).is_none());
+    /// ```
+    pub fn with_anchor(mut self, row: usize, col: usize) -> Option<Self> {
+        if row < self.matrix.get_height() && col < self.matrix.get_width() {
+            self.anchor = (row, col);
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Get the matrix holding the weights of this kernel
+    pub fn matrix(&self) -> &M {
+        &self.matrix
+    }
+
+    /// Get the divisor of this kernel, if one has been set
+    pub fn divisor(&self) -> Option<&K> {
+        self.divisor.as_ref()
+    }
+
+    /// Get the bias of this kernel, if one has been set
+    pub fn bias(&self) -> Option<&K> {
+        self.bias.as_ref()
+    }
+
+    /// Get the `(row, col)` position of the kernel element that sits on top of the output pixel
+    pub fn anchor(&self) -> (usize, usize) {
+        self.anchor
+    }
+
+    /// Consume `self`, and return the matrix, divisor, bias, and anchor (in that order).
+    pub fn into_parts(self) -> (M, Option<K>, Option<K>, (usize, usize)) {
+        (self.matrix, self.divisor, self.bias, self.anchor)
+    }
+}
+
+/// A single element of a sparse kernel.
+///
+/// Sparse kernels are described by a slice of `Tap`s, and applied using
+/// [`write_convolution_sparse`](crate::write_convolution_sparse). Each tap gives the position of
+/// one kernel element relative to the kernel's anchor, along with its weight. For every output
+/// pixel at `(r, c)`, the image value at `(r + row, c + col)` is multiplied by `weight`.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, Tap};
+/// let taps = kernel::sparse(&kernel::laplacian::cross::<i32>());
+/// assert_eq!(taps, vec![
+///     Tap::new(-1, 0, -1),
+///     Tap::new(0, -1, -1),
+///     Tap::new(0, 0, 4),
+///     Tap::new(0, 1, -1),
+///     Tap::new(1, 0, -1),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tap<K> {
+    /// The number of rows between the anchor and this element
+    pub row: isize,
+    /// The number of columns between the anchor and this element
+    pub col: isize,
+    /// The value of this element
+    pub weight: K,
+}
+
+impl<K> Tap<K> {
+    /// Create a new `Tap` at the specified offset from the anchor
+    pub const fn new(row: isize, col: isize, weight: K) -> Self {
+        Self { row, col, weight }
+    }
+}
+
+/// Generate a sparse kernel from the non-zero elements of the provided kernel matrix.
+///
+/// The taps are positioned relative to the center of the matrix, in the same way as
+/// [`write_convolution`](crate::write_convolution), and are returned in row-major order.
+///
+/// The current implementation requires the `"std"` feature flag.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, Tap};
+/// let taps = kernel::sparse(&kernel::sobel::x::<i32>());
+/// assert_eq!(taps.len(), 6);
+/// assert_eq!(taps[0], Tap::new(-1, -1, -1));
+/// ```
+#[cfg(feature = "std")]
+pub fn sparse<K: Zero + Clone>(kernel: &impl Matrix<K>) -> std::vec::Vec<Tap<K>> {
+    let mut taps = dense(kernel);
+    taps.retain(|tap| !tap.weight.is_zero());
+    taps
+}
+
+/// Generate a tap for every element of the provided kernel matrix, including those which are zero,
+/// positioned in the same way as [`sparse`].
+#[cfg(feature = "std")]
+pub(crate) fn dense<K: Clone>(kernel: &impl Matrix<K>) -> std::vec::Vec<Tap<K>> {
+    let (anchor_row, anchor_col) = default_anchor(kernel);
+    let mut taps = std::vec::Vec::new();
+    for row in 0..kernel.get_height() {
+        for col in 0..kernel.get_width() {
+            taps.push(Tap::new(
+                row as isize - anchor_row as isize,
+                col as isize - anchor_col as isize,
+                kernel.get_value(row, col).unwrap().clone(),
+            ));
+        }
+    }
+    taps
+}
+
+/// Generate a flat structuring element from the `true` elements of the provided mask.
+///
+/// Flat structuring elements are used by morphological operations such as
+/// [`dilate`](crate::dilate) and [`erode`](crate::erode), and every tap is given a weight of
+/// `K::default()`. The taps are positioned relative to the center of the mask, in the same way as
+/// [`sparse`].
+///
+/// The current implementation requires the `"std"` feature flag.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, StaticMatrix, Tap};
+/// let mask = StaticMatrix::new(3, 1, [true, false, true]).unwrap();
+/// let taps: Vec<Tap<u8>> = kernel::flat(&mask);
+/// assert_eq!(taps, vec![Tap::new(0, -1, 0), Tap::new(0, 1, 0)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn flat<K: Default>(mask: &impl Matrix<bool>) -> std::vec::Vec<Tap<K>> {
+    let (anchor_row, anchor_col) = default_anchor(mask);
+    let mut taps = std::vec::Vec::new();
+    for row in 0..mask.get_height() {
+        for col in 0..mask.get_width() {
+            if *mask.get_value(row, col).unwrap() {
+                taps.push(Tap::new(
+                    row as isize - anchor_row as isize,
+                    col as isize - anchor_col as isize,
+                    K::default(),
+                ));
+            }
+        }
+    }
+    taps
+}
+
+/// Generate a grayscale structuring element from the provided matrix, in which `None` marks the
+/// elements that are not part of the structuring element.
+///
+/// Unlike [`sparse`], elements with a weight of zero are kept. The taps are positioned relative to
+/// the center of the matrix, in the same way as [`sparse`].
+///
+/// The current implementation requires the `"std"` feature flag.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, StaticMatrix, Tap};
+/// let element = StaticMatrix::new(3, 1, [Some(-1), Some(0), None]).unwrap();
+/// let taps = kernel::structuring_element(&element);
+/// assert_eq!(taps, vec![Tap::new(0, -1, -1), Tap::new(0, 0, 0)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn structuring_element<K: Clone>(element: &impl Matrix<Option<K>>) -> std::vec::Vec<Tap<K>> {
+    let (anchor_row, anchor_col) = default_anchor(element);
+    let mut taps = std::vec::Vec::new();
+    for row in 0..element.get_height() {
+        for col in 0..element.get_width() {
+            if let Some(weight) = element.get_value(row, col).unwrap() {
+                taps.push(Tap::new(
+                    row as isize - anchor_row as isize,
+                    col as isize - anchor_col as isize,
+                    weight.clone(),
+                ));
+            }
+        }
+    }
+    taps
+}
+
+/// The line or point about which a kernel is mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mirror {
+    /// The kernel is mirrored left-to-right, so that `(row, col)` pairs with
+    /// `(row, width - col - 1)`.
+    Horizontal,
+    /// The kernel is mirrored top-to-bottom, so that `(row, col)` pairs with
+    /// `(height - row - 1, col)`.
+    Vertical,
+    /// The kernel is mirrored through its center, so that `(row, col)` pairs with
+    /// `(height - row - 1, width - col - 1)`.
+    Point,
+}
+
+impl Mirror {
+    /// Get the position that the provided position is paired with in a kernel of the specified
+    /// size.
+    pub fn pair(
+        &self,
+        (row, col): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> (usize, usize) {
+        match self {
+            Self::Horizontal => (row, width - col - 1),
+            Self::Vertical => (height - row - 1, col),
+            Self::Point => (height - row - 1, width - col - 1),
+        }
+    }
+}
+
+/// A symmetry present in a kernel, as detected by [`symmetry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    /// The line or point about which the kernel is mirrored
+    pub mirror: Mirror,
+    /// Whether paired values have opposite signs, rather than being equal
+    pub antisymmetric: bool,
+}
+
+/// Detect whether the provided kernel is symmetric (or antisymmetric) about one of its axes or
+/// about its center.
+///
+/// A kernel is symmetric if each value is equal to the value it is paired with by the [`Mirror`],
+/// and antisymmetric if each value is the negation of the value it is paired with. Gaussian, box,
+/// and laplacian kernels are symmetric, while sobel kernels are antisymmetric.
+///
+/// When a kernel has more than one symmetry, the one which requires the fewest multiplications
+/// when used with [`write_convolution_symmetric`](crate::write_convolution_symmetric) is returned.
+/// Returns `None` if the kernel has no symmetry which would reduce the number of multiplications.
+///
+/// # Example
+/// ```
+/// # use convolve2d::kernel::{self, Mirror, Symmetry};
+/// assert_eq!(
+///     kernel::symmetry(&kernel::sobel::x::<i32>()),
+///     Some(Symmetry { mirror: Mirror::Horizontal, antisymmetric: true })
+/// );
+/// assert_eq!(
+///     kernel::symmetry(&kernel::laplacian::full::<i32>()),
+///     Some(Symmetry { mirror: Mirror::Point, antisymmetric: false })
+/// );
+/// ```
+pub fn symmetry<K>(kernel: &impl Matrix<K>) -> Option<Symmetry>
+where
+    K: Add<Output = K> + PartialEq + Zero + Clone,
+{
+    let size = (kernel.get_width(), kernel.get_height());
+    let nonzero = kernel.get_data().iter().filter(|x| !x.is_zero()).count();
+
+    let mut best: Option<(usize, Symmetry)> = None;
+    for mirror in [Mirror::Horizontal, Mirror::Vertical, Mirror::Point] {
+        for antisymmetric in [false, true] {
+            let symmetry = Symmetry {
+                mirror,
+                antisymmetric,
+            };
+            let Some(count) = symmetric_multiplications(kernel, symmetry, size) else {
+                continue;
+            };
+            if count < nonzero && best.is_none_or(|(c, _)| count < c) {
+                best = Some((count, symmetry));
+            }
+        }
+    }
+    best.map(|(_, symmetry)| symmetry)
+}
+
+/// Count the number of multiplications needed to apply the kernel using the provided symmetry,
+/// or `None` if the kernel does not have that symmetry.
+fn symmetric_multiplications<K>(
+    kernel: &impl Matrix<K>,
+    symmetry: Symmetry,
+    size: (usize, usize),
+) -> Option<usize>
+where
+    K: Add<Output = K> + PartialEq + Zero + Clone,
+{
+    let mut count = 0;
+    for row in 0..kernel.get_height() {
+        for col in 0..kernel.get_width() {
+            let value = kernel.get_value(row, col).unwrap();
+            let (r, c) = symmetry.mirror.pair((row, col), size);
+            let other = kernel.get_value(r, c).unwrap();
+
+            let matches = if symmetry.antisymmetric {
+                (value.clone() + other.clone()).is_zero()
+            } else {
+                value == other
+            };
+            if !matches {
+                return None;
+            }
+
+            // Each pair of values is only multiplied once
+            if (row, col) <= (r, c) && !value.is_zero() {
+                count += 1;
+            }
+        }
+    }
+    Some(count)
+}
+
+/// Split a kernel into a column vector and a row vector whose outer product is the kernel.
+///
+/// Applying a separable `n * n` kernel as a vertical and a horizontal pass needs `2n`
+/// multiplications per pixel rather than `n²`. Values are compared with a tolerance relative to
+/// the largest magnitude in the kernel, so kernels such as [`gaussian`] which are only separable
+/// up to rounding error are still detected.
+///
+/// Returns `(column, row)`, with lengths `kernel.get_height()` and `kernel.get_width()`, or `None`
+/// if the kernel is not separable. The current implementation requires the `"std"` feature flag.
+///
+/// # Example
+/// ```
+/// # use convolve2d::kernel;
+/// let (column, row) = kernel::separate(&kernel::sobel::x::<f64>()).unwrap();
+/// assert_eq!(column, vec![-1.0, -2.0, -1.0]);
+/// assert_eq!(row, vec![1.0, 0.0, -1.0]);
+///
+/// assert!(kernel::separate(&kernel::laplacian::cross::<f64>()).is_none());
+/// ```
+#[cfg(feature = "std")]
+pub fn separate(kernel: &impl Matrix<f64>) -> Option<(std::vec::Vec<f64>, std::vec::Vec<f64>)> {
+    let (width, height) = (kernel.get_width(), kernel.get_height());
+    let value = |row, col| *kernel.get_value(row, col).unwrap();
+
+    // Use the largest value as the pivot, to keep the division as accurate as possible
+    let (pivot_row, pivot_col) = (0..height)
+        .flat_map(|row| (0..width).map(move |col| (row, col)))
+        .reduce(|a, b| match value(b.0, b.1).abs() > value(a.0, a.1).abs() {
+            true => b,
+            false => a,
+        })?;
+    let pivot = value(pivot_row, pivot_col);
+    if pivot == 0.0 {
+        return None;
+    }
+
+    let column: std::vec::Vec<f64> = (0..height).map(|row| value(row, pivot_col)).collect();
+    let row: std::vec::Vec<f64> = (0..width)
+        .map(|col| value(pivot_row, col) / pivot)
+        .collect();
+
+    let tolerance = pivot.abs() * 1e-12;
+    for (r, v) in column.iter().enumerate() {
+        for (c, h) in row.iter().enumerate() {
+            if (value(r, c) - v * h).abs() > tolerance {
+                return None;
+            }
+        }
+    }
+    Some((column, row))
+}
+
+/// Get the anchor that the convolution functions use for a plain kernel matrix.
+pub(crate) fn default_anchor<K>(kernel: &impl Matrix<K>) -> (usize, usize) {
+    (
+        ((kernel.get_height() + 1) >> 1).saturating_sub(1),
+        ((kernel.get_width() + 1) >> 1).saturating_sub(1),
+    )
+}
 
 /// Generate a Gaussian kernel with the specified standard deviation.
 ///
@@ -68,6 +518,100 @@ pub fn box_blur(size: usize) -> crate::DynamicMatrix<f64> {
     crate::DynamicMatrix::new(size, size, std::vec![value; size * size]).unwrap()
 }
 
+/// Generate a three dimensional Gaussian kernel with the specified standard deviation.
+///
+/// This is the volumetric counterpart of [`gaussian`], producing a `size * size * size` kernel for
+/// use with [`convolve3d`](crate::convolve3d). The output volume is normalized so that the sum
+/// is 1.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, Volume};
+/// let k = kernel::gaussian_3d(5, 1.0);
+/// assert_eq!(k.get_depth(), 5);
+/// assert!((k.get_data().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+/// assert!(k.get_value(2, 2, 2) > k.get_value(1, 2, 2));
+/// ```
+#[cfg(feature = "std")]
+pub fn gaussian_3d(size: usize, std_dev: f64) -> crate::DynamicVolume<f64> {
+    let stride = (size >> 1) as f64;
+    let exp_coefficient = -0.5 / (std_dev * std_dev);
+    let allocation = size * size * size;
+
+    // Set the values according to the gaussian function
+    let mut data = std::vec![0.0; allocation];
+    for (i, item) in data.iter_mut().enumerate() {
+        let s = (i / (size * size)) as f64 - stride;
+        let r = ((i / size) % size) as f64 - stride;
+        let c = (i % size) as f64 - stride;
+        let x_sq = s * s + r * r + c * c;
+        *item = f64::exp(x_sq * exp_coefficient);
+    }
+
+    // Normalize the values
+    let sum = data.iter().sum::<f64>();
+    if sum > 0.0 {
+        data.iter_mut().for_each(|x| *x /= sum);
+    }
+
+    crate::DynamicVolume::new(size, size, size, data).unwrap()
+}
+
+/// Generate a complex Gabor kernel, commonly used for texture analysis and edge detection.
+///
+/// A Gabor filter is a complex sinusoid with the specified `wavelength` (in pixels), traveling in
+/// the direction given by `orientation` (in radians, measured clockwise from the positive x axis),
+/// under a gaussian envelope with the specified standard deviation. The real part of the output of
+/// a convolution with this kernel responds to symmetric features, while the imaginary part responds
+/// to antisymmetric features. The magnitude of the output can be found using
+/// [`DynamicMatrix::magnitude`](crate::DynamicMatrix::magnitude).
+///
+/// The gaussian envelope is normalized so that its sum is 1.
+///
+/// The current implementation requires the `"std"` feature flag.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, Complex, Matrix};
+/// let k = kernel::gabor(5, 1.0, 4.0, 0.0);
+/// assert_eq!(k.get_value(2, 2).unwrap().im, 0.0);
+///
+/// // A quarter wavelength to the right of the center, the sinusoid is purely imaginary
+/// let right = k.get_value(2, 3).unwrap();
+/// assert!(right.re.abs() < 1e-12 && right.im > 0.0);
+/// ```
+#[cfg(feature = "std")]
+pub fn gabor(
+    size: usize,
+    std_dev: f64,
+    wavelength: f64,
+    orientation: f64,
+) -> crate::DynamicMatrix<crate::Complex<f64>> {
+    let stride = (size >> 1) as f64;
+    let exp_coefficient = -0.5 / (std_dev * std_dev);
+    let (sin, cos) = orientation.sin_cos();
+
+    let mut envelope = std::vec![0.0; size * size];
+    let mut phases = std::vec![0.0; size * size];
+    for (i, (e, p)) in envelope.iter_mut().zip(phases.iter_mut()).enumerate() {
+        let r = (i / size) as f64 - stride;
+        let c = (i % size) as f64 - stride;
+        *e = f64::exp((r * r + c * c) * exp_coefficient);
+        *p = 2.0 * core::f64::consts::PI * (c * cos + r * sin) / wavelength;
+    }
+
+    // Normalize the envelope
+    let sum = envelope.iter().sum::<f64>();
+    let sum = if sum > 0.0 { sum } else { 1.0 };
+
+    let data = envelope
+        .into_iter()
+        .zip(phases)
+        .map(|(e, p)| crate::Complex::from_polar(e / sum, p))
+        .collect();
+    crate::DynamicMatrix::new(size, size, data).unwrap()
+}
+
 /// Sobel filters, commonly used for edge detection
 pub mod sobel {
     use crate::StaticMatrix;
@@ -133,6 +677,71 @@ pub mod sobel {
     }
 }
 
+/// Scharr filters, which respond to edges more evenly across orientations than sobel filters
+pub mod scharr {
+    use crate::StaticMatrix;
+
+    /// A scharr filter that works in the X direction
+    /// 
+    /// This function is generic so that you can choose the data type that works best for you. 
+    /// 
+    /// # Example
+    /// ```
+    /// # use convolve2d::kernel;
+    /// let mat = kernel::scharr::x::<i8>();
+    /// let kernel = [
+    ///      -3, 0,  3,
+    ///     -10, 0, 10,
+    ///      -3, 0,  3,
+    /// ];
+    /// 
+    /// assert_eq!(mat.into_parts().2, kernel);
+    /// ```
+    #[rustfmt::skip]
+    pub fn x<T: From<i8>>() -> StaticMatrix<T, 9> {
+        StaticMatrix::new(
+            3,
+            3,
+            [
+                T::from(-3),  T::from(0), T::from(3),
+                T::from(-10), T::from(0), T::from(10),
+                T::from(-3),  T::from(0), T::from(3),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// A scharr filter that works in the Y direction
+    /// 
+    /// This function is generic so that you can choose the data type that works best for you. 
+    /// 
+    /// # Example
+    /// ```
+    /// # use convolve2d::kernel;
+    /// let mat = kernel::scharr::y::<i8>();
+    /// let kernel = [
+    ///      3,  10,  3,
+    ///      0,   0,  0,
+    ///     -3, -10, -3,
+    /// ];
+    /// 
+    /// assert_eq!(mat.into_parts().2, kernel);
+    /// ```
+    #[rustfmt::skip]
+    pub fn y<T: From<i8>>() -> StaticMatrix<T, 9> {
+        StaticMatrix::new(
+            3,
+            3,
+            [
+                T::from(3),  T::from(10),  T::from(3),
+                T::from(0),  T::from(0),   T::from(0),
+                T::from(-3), T::from(-10), T::from(-3),
+            ],
+        )
+        .unwrap()
+    }
+}
+
 /// Laplacian filters, used for edge detection
 pub mod laplacian {
     use crate::StaticMatrix;
@@ -198,3 +807,63 @@ pub mod laplacian {
         .unwrap()
     }
 }
+
+/// Three dimensional sobel filters, used for edge detection in volumes
+///
+/// Each filter takes the derivative along one axis, while smoothing along the other two. Each
+/// slice of [`x`](sobel_3d::x) and [`y`](sobel_3d::y) is a multiple of the corresponding two
+/// dimensional [`sobel`] filter.
+pub mod sobel_3d {
+    use crate::StaticVolume;
+
+    const DERIVATIVE: [i8; 3] = [-1, 0, 1];
+    const SMOOTHING: [i8; 3] = [1, 2, 1];
+
+    /// Build a 3x3x3 kernel from a weight for each slice, row, and column.
+    fn outer<T: From<i8>>(slices: [i8; 3], rows: [i8; 3], cols: [i8; 3]) -> StaticVolume<T, 27> {
+        let data =
+            core::array::from_fn(|i| T::from(slices[i / 9] * rows[(i / 3) % 3] * cols[i % 3]));
+        StaticVolume::new(3, 3, 3, data).unwrap()
+    }
+
+    /// A sobel filter that works in the X direction
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, Volume};
+    /// let vol = kernel::sobel_3d::x::<i8>();
+    /// assert_eq!(vol.get_value(1, 1, 0), Some(&-4));
+    /// assert_eq!(vol.get_value(0, 0, 2), Some(&1));
+    /// ```
+    pub fn x<T: From<i8>>() -> StaticVolume<T, 27> {
+        outer(SMOOTHING, SMOOTHING, DERIVATIVE)
+    }
+
+    /// A sobel filter that works in the Y direction
+    ///
+    /// As with [`sobel::y`](crate::kernel::sobel::y), the positive weights are at the top.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, Volume};
+    /// let vol = kernel::sobel_3d::y::<i8>();
+    /// assert_eq!(vol.get_value(1, 0, 1), Some(&4));
+    /// assert_eq!(vol.get_value(0, 2, 0), Some(&-1));
+    /// ```
+    pub fn y<T: From<i8>>() -> StaticVolume<T, 27> {
+        outer(SMOOTHING, [1, 0, -1], SMOOTHING)
+    }
+
+    /// A sobel filter that works in the Z direction
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, Volume};
+    /// let vol = kernel::sobel_3d::z::<i8>();
+    /// assert_eq!(vol.get_value(0, 1, 1), Some(&-4));
+    /// assert_eq!(vol.get_value(2, 0, 0), Some(&1));
+    /// ```
+    pub fn z<T: From<i8>>() -> StaticVolume<T, 27> {
+        outer(DERIVATIVE, SMOOTHING, SMOOTHING)
+    }
+}
@@ -21,6 +21,51 @@
 //! * **Kernel Generators**: The [`kernel`] module provides generation functions for a number of
 //!   kernels commonly used in image processing.
 //!
+//! * **Signals**: [`convolve1d`] and [`write_convolution1d`] convolve one dimensional signals, as
+//!   well as individual rows and columns of a `Matrix`.
+//!
+//! * **Streaming**: [`StreamConvolver`] convolves images one row at a time, for images which are
+//!   too large to hold in memory, and [`convolve_tiled`] splits them into tiles which are read
+//!   on demand and processed in parallel.
+//!
+//! * **Volumes**: [`convolve3d`] and [`write_convolution3d`] extend convolutions to three
+//!   dimensional data such as CT scans, using the [`Volume`] trait.
+//!
+//! * **Automatic Strategies**: [`convolve2d_auto`] and [`StrategyPlan`] pick between direct,
+//!   symmetric, separable, and FFT based convolutions, so large kernels are applied efficiently.
+//!
+//! * **Deterministic Floats**: [`convolve2d_compensated`] uses compensated summation and a fixed
+//!   order of operations, giving reproducible results regardless of threading.
+//!
+//! * **Morphology**: [`dilate`], [`erode`], [`open`], [`close`] and friends apply flat or
+//!   grayscale structuring elements, treating the edges of the image in the same way as
+//!   convolutions over a [`Semiring`], while [`min_filter`] and [`max_filter`] handle large
+//!   rectangular windows in constant time per pixel.
+//!
+//! * **Edge Preserving Smoothing**: [`bilateral_filter`] smooths images without blurring their
+//!   edges, and [`bilateral_grid`] approximates it quickly for large windows.
+//!
+//! * **Sharpening**: [`unsharp_mask`] and [`high_boost`] sharpen images of any pixel type,
+//!   clamping the result to the range of integer pixels.
+//!
+//! * **Edge Detection**: [`gradient`] computes the magnitude and orientation of an image's
+//!   gradient, and [`canny`] finds thin, connected edges with the Canny edge detector.
+//!
+//! * **Corner Detection**: [`corners`] finds the strongest corners in an image using the Harris or
+//!   Shi–Tomasi [`CornerResponse`].
+//!
+//! * **Deconvolution**: [`richardson_lucy`] and [`wiener_deconvolution`] undo the blur of a known
+//!   kernel.
+//!
+//! * **Rank Filters**: [`median_filter`], [`percentile_filter`], and [`rank_filter`] remove
+//!   salt-and-pepper noise, using constant time histograms for 8 and 16 bit images.
+//!
+//! * **Custom Filters**: [`neighborhood_map`] computes each pixel from a window of the image,
+//!   handling the image's borders and parallelism, so new nonlinear filters are easy to write.
+//!
+//! * **Overflow Analysis**: [`output_range`] determines the exact range of values an integer kernel
+//!   can produce, so you can choose an accumulator type that will never overflow.
+//!
 //! While other convolution libraries may be more efficient, use a faster algorithm, or run
 //! on the GPU, this library's main focus is providing a complete convolution experience that is
 //! portable and easy to use.
@@ -61,25 +106,89 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "std")]
+mod bilateral;
+mod complex;
 mod convolution;
+mod convolution1d;
+mod convolution3d;
+#[cfg(feature = "std")]
+mod corners;
+#[cfg(feature = "std")]
+mod deconvolution;
+#[cfg(feature = "std")]
+mod edges;
+#[cfg(feature = "std")]
+mod fft;
 #[cfg(feature = "image")]
 mod image_ext;
 mod matrix;
+mod morphology;
+mod neighborhood;
+mod overflow;
+#[cfg(feature = "std")]
+mod plan;
+#[cfg(feature = "std")]
+mod rank;
+mod semiring;
+#[cfg(feature = "std")]
+mod sharpen;
+#[cfg(feature = "std")]
+mod strategy;
+mod streaming;
 mod subpixels;
+#[cfg(feature = "std")]
+mod tiling;
+mod volume;
 
 // Library Public API
 pub mod kernel;
 
 pub use crate::{
-    convolution::{write_convolution, write_convolution_saturating},
+    complex::Complex,
+    convolution::{
+        write_convolution, write_convolution_bank, write_convolution_compensated,
+        write_convolution_kernel, write_convolution_ref, write_convolution_saturating,
+        write_convolution_skip_zeros, write_convolution_sparse, write_convolution_symmetric,
+    },
+    convolution1d::{write_convolution1d, write_convolution1d_line, Line, Mode},
+    convolution3d::{write_convolution3d, write_convolution3d_saturating},
+    kernel::{Kernel, Tap},
     matrix::{Matrix, MatrixMut, StaticMatrix},
+    morphology::{write_dilation, write_erosion},
+    neighborhood::{write_neighborhood_map, Border, Neighborhood},
+    overflow::{output_range, Accumulator, OutputRange},
+    semiring::{write_convolution_semiring, MaxPlus, MinPlus, Semiring, SumProduct},
+    streaming::{stream_ring_len, write_convolution_in_place, StreamConvolver},
     subpixels::SubPixels,
+    volume::{StaticVolume, Volume, VolumeMut},
 };
 
 #[cfg(feature = "std")]
 pub use crate::{
-    convolution::{convolve2d, convolve2d_saturating},
+    bilateral::{bilateral_filter, bilateral_grid},
+    convolution::{
+        convolve2d, convolve2d_bank, convolve2d_batch, convolve2d_compensated, convolve2d_kernel,
+        convolve2d_ref, convolve2d_saturating, convolve2d_skip_zeros, convolve2d_sparse,
+        convolve2d_symmetric, write_convolution_batch,
+    },
+    convolution1d::{convolve1d, convolve1d_line},
+    convolution3d::{convolve3d, convolve3d_saturating},
+    corners::{corner_response, corners, Corner, CornerResponse},
+    deconvolution::{richardson_lucy, wiener_deconvolution},
+    edges::{canny, gradient, GradientOperator},
     matrix::DynamicMatrix,
+    morphology::{
+        close, dilate, erode, max_filter, min_filter, morphological_gradient, open, top_hat,
+    },
+    neighborhood::neighborhood_map,
+    plan::{ConvolutionPlan, PlanOptions, StrategyPlan},
+    rank::{median_filter, percentile_filter, rank_filter, RankFilter},
+    semiring::convolve2d_semiring,
+    sharpen::{high_boost, unsharp_mask},
+    strategy::{convolve2d_auto, Strategy},
+    tiling::{convolve_tiled, write_convolution_tiled, Tile, TileSource},
+    volume::DynamicVolume,
 };
 
 /// A trait for types that can add without overflowing
@@ -91,6 +200,15 @@ pub trait SaturatingAdd<Rhs = Self> {
     fn saturating_add(self, rhs: Rhs) -> Self::Output;
 }
 
+/// A trait for types that can subtract without overflowing
+pub trait SaturatingSub<Rhs = Self> {
+    /// The resulting type after applying subtraction
+    type Output;
+
+    /// Subtract `rhs` from `self`, without overflowing
+    fn saturating_sub(self, rhs: Rhs) -> Self::Output;
+}
+
 /// A trait for types that can be multiplied without overflowing
 pub trait SaturatingMul<Rhs = Self> {
     /// The resulting type after applying multiplication
@@ -100,6 +218,102 @@ pub trait SaturatingMul<Rhs = Self> {
     fn saturating_mul(self, rhs: Rhs) -> Self::Output;
 }
 
+/// A trait for types that can be added with compensation for rounding error
+///
+/// This is used by the [`_compensated`](crate::write_convolution_compensated) API to implement
+/// Neumaier summation, which keeps track of the low order bits lost by each addition so that they
+/// can be added back at the end.
+pub trait CompensatedAdd: Sized {
+    /// Add `rhs` to `self`, adding the rounding error of the addition to `compensation`
+    fn compensated_add(self, rhs: Self, compensation: &mut Self) -> Self;
+}
+
+/// A trait for types which can be ordered to find the largest and smallest of two values
+///
+/// This is used by the max-plus and min-plus [semirings](crate::Semiring), and by the
+/// morphological operations such as [`dilate`] and [`erode`]. Types made up of several values,
+/// such as [`SubPixels`], are ordered one value at a time.
+pub trait Lattice: Sized {
+    /// The smallest possible value, which is never greater than any other value
+    fn bottom() -> Self;
+
+    /// The largest possible value, which is never less than any other value
+    fn top() -> Self;
+
+    /// The larger of `self` and `rhs`
+    fn sup(self, rhs: Self) -> Self;
+
+    /// The smaller of `self` and `rhs`
+    fn inf(self, rhs: Self) -> Self;
+}
+
+/// A trait for pixel types made up of one or more numeric channels
+///
+/// This is used by filters which need to compare pixels with each other, such as the
+/// [`bilateral_filter`], and so work with each channel as an `f64`.
+/// Integer channels are rounded to the nearest value when converted back, saturating at the
+/// limits of their type. An `f64` only holds integers of up to 53 bits exactly, so channels of
+/// 64 and 128 bit integers (and `usize` or `isize` on 64 bit targets) are rounded to the nearest
+/// representable value when their magnitude exceeds `2^53`.
+pub trait Channels: Sized {
+    /// The number of channels in each pixel
+    const CHANNELS: usize;
+
+    /// Get the value of the channel with the specified index, in the range `0..Self::CHANNELS`
+    fn channel(&self, index: usize) -> f64;
+
+    /// Create a pixel by calling `channel` with the index of each of its channels in turn
+    fn from_channels(channel: impl FnMut(usize) -> f64) -> Self;
+}
+
+/// A trait for types that can be sent between threads, if the `rayon` feature is enabled.
+///
+/// When `rayon` is enabled this is equivalent to [`Send`], and otherwise it is implemented for
+/// every type. This allows the convolution functions to accept types which are not thread safe
+/// when they will not be run in parallel.
+#[cfg(feature = "rayon")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "rayon")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// A trait for types that can be sent between threads, if the `rayon` feature is enabled.
+///
+/// When `rayon` is enabled this is equivalent to [`Send`], and otherwise it is implemented for
+/// every type. This allows the convolution functions to accept types which are not thread safe
+/// when they will not be run in parallel.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// A trait for types that can be shared between threads, if the `rayon` feature is enabled.
+///
+/// When `rayon` is enabled this is equivalent to [`Sync`], and otherwise it is implemented for
+/// every type.
+#[cfg(feature = "rayon")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "rayon")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// A trait for types that can be shared between threads, if the `rayon` feature is enabled.
+///
+/// When `rayon` is enabled this is equivalent to [`Sync`], and otherwise it is implemented for
+/// every type.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// A trait for types that have a notion of zero
+///
+/// This is used by [`kernel::sparse`] to leave zero valued kernel elements out of a sparse kernel,
+/// by [`write_convolution_skip_zeros`] to skip them in a dense kernel, and by
+/// [`kernel::symmetry`] to recognise antisymmetric kernels.
+pub trait Zero {
+    /// Check whether `self` is equal to zero
+    fn is_zero(&self) -> bool;
+}
+
 macro_rules! saturating_impl {
     ($($t:ty),+) => {
         $(
@@ -112,6 +326,15 @@ macro_rules! saturating_impl {
                 }
             }
 
+            impl SaturatingSub<$t> for $t {
+                type Output = Self;
+
+                #[inline]
+                fn saturating_sub(self, v: Self) -> Self {
+                    <$t>::saturating_sub(self, v)
+                }
+            }
+
             impl SaturatingMul<$t> for $t {
                 type Output = Self;
 
@@ -126,3 +349,176 @@ macro_rules! saturating_impl {
 
 saturating_impl!(u8, u16, u32, u64, u128, usize);
 saturating_impl!(i8, i16, i32, i64, i128, isize);
+
+/// Floating point addition and subtraction already saturate, at positive or negative infinity.
+macro_rules! float_saturating_impl {
+    ($($t:ty),+) => {
+        $(
+            impl SaturatingAdd<$t> for $t {
+                type Output = Self;
+
+                #[inline]
+                fn saturating_add(self, v: Self) -> Self {
+                    self + v
+                }
+            }
+
+            impl SaturatingSub<$t> for $t {
+                type Output = Self;
+
+                #[inline]
+                fn saturating_sub(self, v: Self) -> Self {
+                    self - v
+                }
+            }
+        )+
+    };
+}
+
+float_saturating_impl!(f32, f64);
+
+macro_rules! zero_impl {
+    ($($t:ty),+) => {
+        $(
+            impl Zero for $t {
+                #[inline]
+                fn is_zero(&self) -> bool {
+                    *self == 0 as $t
+                }
+            }
+        )+
+    };
+}
+
+zero_impl!(u8, u16, u32, u64, u128, usize);
+zero_impl!(i8, i16, i32, i64, i128, isize);
+zero_impl!(f32, f64);
+
+macro_rules! compensated_impl {
+    ($($t:ty),+) => {
+        $(
+            impl CompensatedAdd for $t {
+                #[inline]
+                fn compensated_add(self, rhs: Self, compensation: &mut Self) -> Self {
+                    let sum = self + rhs;
+                    if self.abs() >= rhs.abs() {
+                        *compensation += (self - sum) + rhs;
+                    } else {
+                        *compensation += (rhs - sum) + self;
+                    }
+                    sum
+                }
+            }
+        )+
+    };
+}
+
+compensated_impl!(f32, f64);
+
+macro_rules! lattice_impl {
+    ($($t:ty),+) => {
+        $(
+            impl Lattice for $t {
+                #[inline]
+                fn bottom() -> Self {
+                    <$t>::MIN
+                }
+
+                #[inline]
+                fn top() -> Self {
+                    <$t>::MAX
+                }
+
+                #[inline]
+                fn sup(self, rhs: Self) -> Self {
+                    core::cmp::Ord::max(self, rhs)
+                }
+
+                #[inline]
+                fn inf(self, rhs: Self) -> Self {
+                    core::cmp::Ord::min(self, rhs)
+                }
+            }
+        )+
+    };
+}
+
+lattice_impl!(u8, u16, u32, u64, u128, usize);
+lattice_impl!(i8, i16, i32, i64, i128, isize);
+
+macro_rules! float_lattice_impl {
+    ($($t:ty),+) => {
+        $(
+            impl Lattice for $t {
+                #[inline]
+                fn bottom() -> Self {
+                    <$t>::NEG_INFINITY
+                }
+
+                #[inline]
+                fn top() -> Self {
+                    <$t>::INFINITY
+                }
+
+                #[inline]
+                fn sup(self, rhs: Self) -> Self {
+                    <$t>::max(self, rhs)
+                }
+
+                #[inline]
+                fn inf(self, rhs: Self) -> Self {
+                    <$t>::min(self, rhs)
+                }
+            }
+        )+
+    };
+}
+
+float_lattice_impl!(f32, f64);
+
+macro_rules! channels_impl {
+    ($($t:ty),+) => {
+        $(
+            impl Channels for $t {
+                const CHANNELS: usize = 1;
+
+                #[inline]
+                fn channel(&self, _index: usize) -> f64 {
+                    *self as f64
+                }
+
+                #[inline]
+                fn from_channels(mut channel: impl FnMut(usize) -> f64) -> Self {
+                    channel(0) as $t
+                }
+            }
+        )+
+    };
+}
+
+channels_impl!(f32, f64);
+
+macro_rules! int_channels_impl {
+    ($($t:ty),+) => {
+        $(
+            impl Channels for $t {
+                const CHANNELS: usize = 1;
+
+                #[inline]
+                fn channel(&self, _index: usize) -> f64 {
+                    *self as f64
+                }
+
+                #[inline]
+                fn from_channels(mut channel: impl FnMut(usize) -> f64) -> Self {
+                    // Round half away from zero without `f64::round`, which needs `std`. Float to
+                    // integer casts truncate and saturate, so this also clamps to the type's range.
+                    let value = channel(0);
+                    (value + 0.5f64.copysign(value)) as $t
+                }
+            }
+        )+
+    };
+}
+
+int_channels_impl!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
@@ -41,6 +41,31 @@ pub trait Matrix<T> {
     }
 }
 
+/// References to matrices are matrices too. This allows kernels of different types to be used
+/// together as `&dyn Matrix<T>`, such as in
+/// [`write_convolution_bank`](crate::write_convolution_bank).
+impl<T, M: Matrix<T> + ?Sized> Matrix<T> for &M {
+    #[inline]
+    fn get_width(&self) -> usize {
+        (**self).get_width()
+    }
+
+    #[inline]
+    fn get_height(&self) -> usize {
+        (**self).get_height()
+    }
+
+    #[inline]
+    fn get_data(&self) -> &[T] {
+        (**self).get_data()
+    }
+
+    #[inline]
+    fn get_value(&self, row: usize, col: usize) -> Option<&T> {
+        (**self).get_value(row, col)
+    }
+}
+
 /// A subtype of [`Matrix`] allowing mutable access to the underlying data.
 ///
 /// # Extensibility
@@ -58,6 +83,27 @@ pub trait MatrixMut<T>: Matrix<T> {
 #[repr(transparent)]
 pub(crate) struct FlippedMatrix<'a, M>(pub &'a M);
 
+impl<M> Clone for FlippedMatrix<'_, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for FlippedMatrix<'_, M> {}
+
+impl<'a, M> FlippedMatrix<'a, M> {
+    /// Get the value at the given row and column of the flipped matrix, borrowed for the lifetime
+    /// of the original matrix rather than of `self`.
+    pub(crate) fn get_ref<T>(self, row: usize, col: usize) -> Option<&'a T>
+    where
+        M: Matrix<T>,
+    {
+        let new_row = self.0.get_height() - row - 1;
+        let new_col = self.0.get_width() - col - 1;
+        self.0.get_value(new_row, new_col)
+    }
+}
+
 impl<'a, M, T> Matrix<T> for FlippedMatrix<'a, M>
 where
     M: Matrix<T>,
@@ -0,0 +1,597 @@
+//! Mathematical morphology. Dilation and erosion share the tap loop of the
+//! [semiring](crate::Semiring) convolutions, so they treat the edges of the image the same way.
+
+use crate::kernel::Tap;
+use crate::matrix::{Matrix, MatrixMut};
+use crate::semiring::accumulate_taps;
+use crate::{Lattice, MaxPlus, MaybeSend, MaybeSync, SaturatingAdd, SaturatingSub, Semiring};
+#[cfg(all(feature = "std", feature = "rayon"))]
+use rayon::prelude::*;
+
+// Re-import the standard library
+#[cfg(feature = "std")]
+use crate::DynamicMatrix;
+#[cfg(feature = "std")]
+use std::prelude::v1::*;
+
+/// Dilate the specified image with the provided structuring element.
+///
+/// This function is a convient interface for the [`write_dilation`] function, automatically
+/// generating a new allocation in which to store the result.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{dilate, kernel, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(5, 1, vec![0u8, 0, 7, 0, 0]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = dilate(&image, &element);
+/// assert_eq!(output.get_data(), &[0, 7, 7, 7, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn dilate<T, K, O>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<O>
+where
+    T: SaturatingAdd<K, Output = O> + Clone + MaybeSync,
+    K: Clone + MaybeSync,
+    O: Lattice + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let data = (0..allocation).map(|_| O::bottom()).collect();
+    let mut out = DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap();
+    write_dilation(image, element, &mut out);
+    out
+}
+
+/// Write the dilation of the provided image by a structuring element into the specified buffer.
+///
+/// Each output pixel is the largest of the values already in `out` and the image values beneath
+/// the structuring element, each increased by the element's weight. Structuring elements are
+/// described as a slice of [`Tap`]s, and can be generated from a mask with
+/// [`kernel::flat`](crate::kernel::flat) or from a matrix of weights with
+/// [`kernel::structuring_element`](crate::kernel::structuring_element).
+///
+/// As is conventional, the structuring element is reflected through its anchor, which makes this
+/// the max-plus convolution of the image and the element. Parts of the structuring element which
+/// fall outside of the image are ignored. The weights are added with saturating arithmetic, so
+/// integer images are clamped to the range of their type.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_dilation, StaticMatrix, Tap};
+/// let image = StaticMatrix::new(4, 1, [0, 5, 0, 0]).unwrap();
+/// let element = [Tap::new(0, 0, 0), Tap::new(0, 1, -1)];
+///
+/// let mut output = StaticMatrix::new(4, 1, [i32::MIN; 4]).unwrap();
+/// write_dilation(&image, &element, &mut output);
+/// assert_eq!(output.into_parts().2, [0, 5, 4, 0]);
+/// ```
+///
+/// # Panics
+/// If the dimensions of `out` do not match those of `image`.
+pub fn write_dilation<T, K, O>(
+    image: &impl Matrix<T>,
+    element: &[Tap<K>],
+    out: &mut impl MatrixMut<O>,
+) where
+    T: SaturatingAdd<K, Output = O> + Clone + MaybeSync,
+    K: Clone + MaybeSync,
+    O: Lattice + Clone + MaybeSend,
+{
+    accumulate_taps(image, element, true, out, |acc, value, weight| {
+        Semiring::<T, K>::add(&MaxPlus, acc, MaxPlus.mul(value, weight))
+    });
+}
+
+/// Erode the specified image with the provided structuring element.
+///
+/// This function is a convient interface for the [`write_erosion`] function, automatically
+/// generating a new allocation in which to store the result.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{erode, kernel, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(5, 1, vec![9u8, 9, 2, 9, 9]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = erode(&image, &element);
+/// assert_eq!(output.get_data(), &[9, 2, 2, 2, 9]);
+/// ```
+#[cfg(feature = "std")]
+pub fn erode<T, K, O>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<O>
+where
+    T: SaturatingSub<K, Output = O> + Clone + MaybeSync,
+    K: Clone + MaybeSync,
+    O: Lattice + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let data = (0..allocation).map(|_| O::top()).collect();
+    let mut out = DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap();
+    write_erosion(image, element, &mut out);
+    out
+}
+
+/// Write the erosion of the provided image by a structuring element into the specified buffer.
+///
+/// Each output pixel is the smallest of the values already in `out` and the image values beneath
+/// the structuring element, each decreased by the element's weight. Parts of the structuring
+/// element which fall outside of the image are ignored. The weights are subtracted with saturating
+/// arithmetic, so integer images are clamped to the range of their type.
+///
+/// Unlike [`write_dilation`], which is the [`MaxPlus`] convolution of the image and the element,
+/// this subtracts the weights rather than using [`MinPlus`](crate::MinPlus), so the weights do not
+/// need to support negation.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_erosion, StaticMatrix, Tap};
+/// let image = StaticMatrix::new(4, 1, [9, 5, 9, 9]).unwrap();
+/// let element = [Tap::new(0, 0, 0), Tap::new(0, 1, 1)];
+///
+/// let mut output = StaticMatrix::new(4, 1, [i32::MAX; 4]).unwrap();
+/// write_erosion(&image, &element, &mut output);
+/// assert_eq!(output.into_parts().2, [4, 5, 8, 9]);
+/// ```
+///
+/// # Panics
+/// If the dimensions of `out` do not match those of `image`.
+pub fn write_erosion<T, K, O>(
+    image: &impl Matrix<T>,
+    element: &[Tap<K>],
+    out: &mut impl MatrixMut<O>,
+) where
+    T: SaturatingSub<K, Output = O> + Clone + MaybeSync,
+    K: Clone + MaybeSync,
+    O: Lattice + Clone + MaybeSend,
+{
+    accumulate_taps(image, element, false, out, |acc, value, weight| {
+        acc.inf(value.clone().saturating_sub(weight.clone()))
+    });
+}
+
+/// Open the specified image with the provided structuring element, by eroding and then dilating
+/// it.
+///
+/// Opening removes bright features which are smaller than the structuring element, while leaving
+/// larger features unchanged.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, open, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(7, 1, vec![0u8, 8, 0, 5, 5, 5, 0]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = open(&image, &element);
+/// assert_eq!(output.get_data(), &[0, 0, 0, 5, 5, 5, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn open<T, K>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<T>
+where
+    T: SaturatingAdd<K, Output = T>
+        + SaturatingSub<K, Output = T>
+        + Lattice
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Clone + MaybeSync,
+{
+    dilate(&erode(image, element), element)
+}
+
+/// Close the specified image with the provided structuring element, by dilating and then eroding
+/// it.
+///
+/// Closing fills in dark features which are smaller than the structuring element, while leaving
+/// larger features unchanged.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{close, kernel, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(7, 1, vec![9u8, 9, 1, 9, 9, 9, 9]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = close(&image, &element);
+/// assert_eq!(output.get_data(), &[9; 7]);
+/// ```
+#[cfg(feature = "std")]
+pub fn close<T, K>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<T>
+where
+    T: SaturatingAdd<K, Output = T>
+        + SaturatingSub<K, Output = T>
+        + Lattice
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Clone + MaybeSync,
+{
+    erode(&dilate(image, element), element)
+}
+
+/// Compute the morphological gradient of the specified image, which is the difference between its
+/// dilation and its erosion.
+///
+/// The gradient is large wherever the image changes within the structuring element, so it
+/// highlights the outlines of objects.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, morphological_gradient, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(6, 1, vec![1u8, 1, 1, 6, 6, 6]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = morphological_gradient(&image, &element);
+/// assert_eq!(output.get_data(), &[0, 0, 5, 5, 0, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn morphological_gradient<T, K>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<T>
+where
+    T: SaturatingAdd<K, Output = T>
+        + SaturatingSub<K, Output = T>
+        + SaturatingSub<Output = T>
+        + Lattice
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Clone + MaybeSync,
+{
+    let dilated = dilate(image, element);
+    let eroded: DynamicMatrix<T> = erode(image, element);
+    let data = dilated
+        .get_data()
+        .iter()
+        .zip(eroded.get_data())
+        .map(|(d, e)| d.clone().saturating_sub(e.clone()))
+        .collect();
+    DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap()
+}
+
+/// Compute the (white) top-hat transform of the specified image, which is the difference between
+/// the image and its [opening](open).
+///
+/// The top-hat keeps only the bright features which are smaller than the structuring element,
+/// which makes it useful for correcting uneven backgrounds.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, top_hat, DynamicMatrix, Matrix, StaticMatrix};
+/// let image = DynamicMatrix::new(7, 1, vec![2u8, 9, 2, 5, 5, 5, 2]).unwrap();
+/// let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+///
+/// let output = top_hat(&image, &element);
+/// assert_eq!(output.get_data(), &[0, 7, 0, 0, 0, 0, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn top_hat<T, K>(image: &impl Matrix<T>, element: &[Tap<K>]) -> DynamicMatrix<T>
+where
+    T: SaturatingAdd<K, Output = T>
+        + SaturatingSub<K, Output = T>
+        + SaturatingSub<Output = T>
+        + Lattice
+        + Clone
+        + MaybeSend
+        + MaybeSync,
+    K: Clone + MaybeSync,
+{
+    let opened = open(image, element);
+    let data = image
+        .get_data()
+        .iter()
+        .zip(opened.get_data())
+        .map(|(x, o)| x.clone().saturating_sub(o.clone()))
+        .collect();
+    DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap()
+}
+
+/// Find the smallest value within a rectangular window around each pixel of the specified image.
+///
+/// The window extends `horizontal_radius` columns to either side of each pixel, and
+/// `vertical_radius` rows above and below it. Parts of the window which fall outside of the image
+/// are ignored, so this gives the same result as [`erode`] with a flat rectangular structuring
+/// element. However, the van Herk/Gil-Werman algorithm is used, which takes a fixed number of
+/// comparisons per pixel regardless of the size of the window. Types made up of several values,
+/// such as [`SubPixels`](crate::SubPixels), are filtered one channel at a time.
+///
+/// Rows, and then columns, are filtered in parallel when the `rayon` feature is enabled.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{min_filter, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(3, 3, vec![
+///     5, 6, 7,
+///     4, 9, 8,
+///     3, 2, 1,
+/// ]).unwrap();
+///
+/// let output = min_filter(&image, 1, 0);
+/// assert_eq!(output.get_data(), &[5, 5, 6, 4, 4, 8, 2, 1, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn min_filter<T>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+) -> DynamicMatrix<T>
+where
+    T: Lattice + Clone + MaybeSend + MaybeSync,
+{
+    extreme_filter(image, horizontal_radius, vertical_radius, T::top, T::inf)
+}
+
+/// Find the largest value within a rectangular window around each pixel of the specified image.
+///
+/// This is the counterpart of [`min_filter`], and gives the same result as [`dilate`] with a flat
+/// rectangular structuring element, in a fixed number of comparisons per pixel.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{max_filter, DynamicMatrix, Matrix, SubPixels};
+/// let image = DynamicMatrix::new(1, 3, vec![
+///     SubPixels([1u8, 9]),
+///     SubPixels([5, 3]),
+///     SubPixels([2, 4]),
+/// ]).unwrap();
+///
+/// let output = max_filter(&image, 0, 1);
+/// assert_eq!(output.get_data(), &[SubPixels([5, 9]), SubPixels([5, 9]), SubPixels([5, 4])]);
+/// ```
+#[cfg(feature = "std")]
+pub fn max_filter<T>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+) -> DynamicMatrix<T>
+where
+    T: Lattice + Clone + MaybeSend + MaybeSync,
+{
+    extreme_filter(image, horizontal_radius, vertical_radius, T::bottom, T::sup)
+}
+
+/// Apply a separable sliding window filter, using `pick` to choose between two values and
+/// `identity` to fill the parts of the window outside of the image.
+#[cfg(feature = "std")]
+fn extreme_filter<T>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+    identity: fn() -> T,
+    pick: fn(T, T) -> T,
+) -> DynamicMatrix<T>
+where
+    T: Clone + MaybeSend + MaybeSync,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    if width == 0 || height == 0 {
+        return DynamicMatrix::new(width, height, image.get_data().to_vec()).unwrap();
+    }
+
+    // Filter the rows, then transpose so that the columns can be filtered as rows too
+    let rows = filter_lines(image.get_data(), width, horizontal_radius, identity, pick);
+    let columns = filter_lines(
+        &transpose(&rows, width),
+        height,
+        vertical_radius,
+        identity,
+        pick,
+    );
+    DynamicMatrix::new(width, height, transpose(&columns, height)).unwrap()
+}
+
+/// Filter each line of `len` values in `data` independently.
+#[cfg(feature = "std")]
+fn filter_lines<T>(
+    data: &[T],
+    len: usize,
+    radius: usize,
+    identity: fn() -> T,
+    pick: fn(T, T) -> T,
+) -> Vec<T>
+where
+    T: Clone + MaybeSend + MaybeSync,
+{
+    let mut out = data.to_vec();
+
+    #[cfg(not(feature = "rayon"))]
+    let lines = out.chunks_mut(len).zip(data.chunks(len));
+    #[cfg(feature = "rayon")]
+    let lines = out.par_chunks_mut(len).zip(data.par_chunks(len));
+
+    lines.for_each(|(out, line)| van_herk(line, radius, identity, pick, out));
+    out
+}
+
+/// Apply the van Herk/Gil-Werman algorithm to a single line.
+///
+/// The line is padded with `radius` identity values on either side, and split into blocks the
+/// size of the window. Each window then covers the end of one block and the start of the next, so
+/// its extreme value can be found from one suffix and one prefix of the blocks.
+#[cfg(feature = "std")]
+fn van_herk<T: Clone>(
+    line: &[T],
+    radius: usize,
+    identity: fn() -> T,
+    pick: fn(T, T) -> T,
+    out: &mut [T],
+) {
+    let window = 2 * radius + 1;
+    let padded_len = line.len() + 2 * radius;
+    let padded = |i: usize| match i.checked_sub(radius) {
+        Some(i) if i < line.len() => line[i].clone(),
+        _ => identity(),
+    };
+
+    let mut prefix: Vec<T> = Vec::with_capacity(padded_len);
+    for i in 0..padded_len {
+        let value = match i % window {
+            0 => padded(i),
+            _ => pick(prefix[i - 1].clone(), padded(i)),
+        };
+        prefix.push(value);
+    }
+
+    let mut suffix: Vec<T> = (0..padded_len).map(padded).collect();
+    for i in (0..padded_len - 1).rev() {
+        if (i + 1) % window != 0 {
+            suffix[i] = pick(suffix[i].clone(), suffix[i + 1].clone());
+        }
+    }
+
+    for (i, value) in out.iter_mut().enumerate() {
+        *value = pick(suffix[i].clone(), prefix[i + window - 1].clone());
+    }
+}
+
+/// Transpose a row-major buffer with rows of `width` values.
+#[cfg(feature = "std")]
+pub(crate) fn transpose<T: Clone>(data: &[T], width: usize) -> Vec<T> {
+    let height = data.len() / width;
+    (0..width)
+        .flat_map(|col| (0..height).map(move |row| data[row * width + col].clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        close, dilate, erode, max_filter, min_filter, morphological_gradient, open, top_hat,
+    };
+    use crate::{
+        convolve2d_semiring, kernel, DynamicMatrix, Matrix, MaxPlus, StaticMatrix, SubPixels, Tap,
+    };
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    fn image() -> DynamicMatrix<i32> {
+        let data = (0..6 * 5).map(|i| (i * 37) % 11).collect();
+        DynamicMatrix::new(6, 5, data).unwrap()
+    }
+
+    #[test]
+    fn dilation_is_reflected_max_plus() {
+        let element = kernel::structuring_element(
+            &StaticMatrix::new(2, 2, [Some(1), None, Some(-2), Some(0)]).unwrap(),
+        );
+        let reflected: Vec<_> = element
+            .iter()
+            .map(|tap| Tap::new(-tap.row, -tap.col, tap.weight))
+            .collect();
+
+        let expected: DynamicMatrix<i32> = convolve2d_semiring(&image(), &reflected, &MaxPlus);
+        assert_eq!(dilate(&image(), &element), expected);
+    }
+
+    #[test]
+    fn erosion_is_dual_of_dilation() {
+        let element = kernel::flat::<i32>(&StaticMatrix::new(3, 2, [true; 6]).unwrap());
+        let reflected: Vec<_> = element
+            .iter()
+            .map(|tap| Tap::new(-tap.row, -tap.col, tap.weight))
+            .collect();
+        let negated = image().get_data().iter().map(|x| -x).collect();
+        let negated = DynamicMatrix::new(6, 5, negated).unwrap();
+
+        let eroded: DynamicMatrix<i32> = erode(&image(), &reflected);
+        let dilated: DynamicMatrix<i32> = dilate(&negated, &element);
+        let dual: Vec<_> = dilated.get_data().iter().map(|x| -x).collect();
+        assert_eq!(eroded.get_data(), &dual[..]);
+    }
+
+    #[test]
+    fn grayscale_element_saturates() {
+        let image = DynamicMatrix::new(4, 1, vec![0u8, 250, 10, 255]).unwrap();
+        let element = kernel::structuring_element(
+            &StaticMatrix::new(3, 1, [Some(20u8), Some(0), Some(20)]).unwrap(),
+        );
+
+        let dilated: DynamicMatrix<u8> = dilate(&image, &element);
+        assert_eq!(dilated.get_data(), &[255, 250, 255, 255]);
+        let eroded: DynamicMatrix<u8> = erode(&image, &element);
+        assert_eq!(eroded.get_data(), &[0, 0, 10, 0]);
+        assert_eq!(
+            morphological_gradient(&image, &element).get_data(),
+            &[255, 250, 245, 255]
+        );
+    }
+
+    #[test]
+    fn open_and_close_are_idempotent() {
+        let element = kernel::flat::<i32>(&StaticMatrix::new(3, 3, [true; 9]).unwrap());
+        let opened = open(&image(), &element);
+        let closed = close(&image(), &element);
+        assert_eq!(open(&opened, &element), opened);
+        assert_eq!(close(&closed, &element), closed);
+        assert!(opened
+            .get_data()
+            .iter()
+            .zip(image().get_data())
+            .all(|(o, x)| o <= x));
+        assert!(closed
+            .get_data()
+            .iter()
+            .zip(image().get_data())
+            .all(|(c, x)| c >= x));
+    }
+
+    #[test]
+    fn subpixels() {
+        let image = DynamicMatrix::new(
+            3,
+            1,
+            vec![
+                SubPixels::<u8, 2>([1, 8]),
+                SubPixels([6, 2]),
+                SubPixels([3, 5]),
+            ],
+        )
+        .unwrap();
+        let element = kernel::flat::<u8>(&StaticMatrix::new(3, 1, [true; 3]).unwrap());
+
+        let output = top_hat(&image, &element);
+        assert_eq!(
+            output.get_data(),
+            &[SubPixels([0, 6]), SubPixels([3, 0]), SubPixels([0, 3])]
+        );
+    }
+
+    #[test_case(0, 0; "identity")]
+    #[test_case(1, 2; "small")]
+    #[test_case(3, 0; "horizontal")]
+    #[test_case(0, 4; "vertical")]
+    #[test_case(9, 9; "larger_than_image")]
+    fn filters_match_flat_elements(horizontal_radius: usize, vertical_radius: usize) {
+        let (w, h) = (2 * horizontal_radius + 1, 2 * vertical_radius + 1);
+        let element = kernel::flat::<i32>(&DynamicMatrix::new(w, h, vec![true; w * h]).unwrap());
+
+        let eroded: DynamicMatrix<i32> = erode(&image(), &element);
+        let dilated: DynamicMatrix<i32> = dilate(&image(), &element);
+        assert_eq!(
+            min_filter(&image(), horizontal_radius, vertical_radius),
+            eroded
+        );
+        assert_eq!(
+            max_filter(&image(), horizontal_radius, vertical_radius),
+            dilated
+        );
+    }
+
+    #[test]
+    fn empty_image() {
+        let image = DynamicMatrix::<u8>::new(0, 3, vec![]).unwrap();
+        assert_eq!(min_filter(&image, 2, 2), image);
+    }
+}
@@ -0,0 +1,308 @@
+//! A generic interface for filters which compute each output pixel from a window of the input.
+
+use crate::matrix::{Matrix, MatrixMut};
+use crate::{MaybeSend, MaybeSync};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// Re-import the standard library
+#[cfg(feature = "std")]
+use crate::DynamicMatrix;
+#[cfg(feature = "std")]
+use std::prelude::v1::*;
+
+/// The way in which pixels beyond the edges of an image are filled in.
+///
+/// # Example
+/// ```
+/// # use convolve2d::Border;
+/// // For a row of pixels `a b c d`, the two pixels to the left of `a` are:
+/// let constant = Border::Constant(0);  // 0 0
+/// let replicate = Border::<i32>::Replicate; // a a
+/// let reflect = Border::<i32>::Reflect; // c b
+/// let wrap = Border::<i32>::Wrap; // c d
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Border<T> {
+    /// Every pixel beyond the edges has the same value
+    Constant(T),
+    /// Each pixel beyond the edges is a copy of the nearest pixel on the edge
+    Replicate,
+    /// The image is mirrored about its edge pixels, without repeating them
+    Reflect,
+    /// The image repeats, so that the pixels beyond one edge come from the opposite edge
+    Wrap,
+}
+
+impl<T> Border<T> {
+    /// Find the index of the pixel which should be read in place of `index`, along an axis with
+    /// `len` pixels, or `None` if the constant value should be used.
+    fn resolve(&self, index: isize, len: usize) -> Option<usize> {
+        let len = len as isize;
+        if (0..len).contains(&index) {
+            return Some(index as usize);
+        }
+        match self {
+            Border::Constant(_) => None,
+            Border::Replicate => Some(index.clamp(0, len - 1) as usize),
+            Border::Reflect => {
+                let period = 2 * (len - 1);
+                if period == 0 {
+                    return Some(0);
+                }
+                let index = index.rem_euclid(period);
+                Some(index.min(period - index) as usize)
+            }
+            Border::Wrap => Some(index.rem_euclid(len) as usize),
+        }
+    }
+}
+
+/// A view of the window of an image around one pixel, as passed to [`neighborhood_map`].
+///
+/// Positions within the window are given relative to its top left corner, and pixels beyond the
+/// edges of the image are filled in according to the [`Border`].
+#[derive(Debug)]
+pub struct Neighborhood<'a, T> {
+    /// The data of the whole image
+    data: &'a [T],
+    /// The `(width, height)` of the whole image
+    image_size: (usize, usize),
+    /// The `(width, height)` of the window
+    size: (usize, usize),
+    /// The position in the image of the window's top left corner
+    origin: (isize, isize),
+    /// The `(row, col)` position of the window's anchor within the window
+    anchor: (usize, usize),
+    /// How pixels beyond the edges of the image are filled in
+    border: &'a Border<T>,
+}
+
+impl<T> Clone for Neighborhood<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Neighborhood<'_, T> {}
+
+impl<'a, T> Neighborhood<'a, T> {
+    /// Get the width of the window
+    pub fn width(&self) -> usize {
+        self.size.0
+    }
+
+    /// Get the height of the window
+    pub fn height(&self) -> usize {
+        self.size.1
+    }
+
+    /// Get the `(row, col)` position in the image of the pixel being computed
+    pub fn position(&self) -> (usize, usize) {
+        (
+            (self.origin.0 + self.anchor.0 as isize) as usize,
+            (self.origin.1 + self.anchor.1 as isize) as usize,
+        )
+    }
+
+    /// Get the value at the specified position within the window, or `None` if the position is
+    /// outside of the window.
+    pub fn get(&self, row: usize, col: usize) -> Option<&'a T> {
+        if row >= self.size.1 || col >= self.size.0 {
+            return None;
+        }
+        let (width, height) = self.image_size;
+        let r = self.border.resolve(self.origin.0 + row as isize, height);
+        let c = self.border.resolve(self.origin.1 + col as isize, width);
+        match (r, c, self.border) {
+            (Some(r), Some(c), _) => Some(&self.data[r * width + c]),
+            (_, _, Border::Constant(value)) => Some(value),
+            _ => unreachable!("Only constant borders leave pixels unresolved"),
+        }
+    }
+
+    /// Get the value of the pixel being computed, which sits under the window's anchor
+    pub fn center(&self) -> &'a T {
+        self.get(self.anchor.0, self.anchor.1).unwrap()
+    }
+
+    /// Iterate over the values in the window, in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        (0..self.size.1)
+            .flat_map(move |row| (0..self.size.0).map(move |col| self.get(row, col).unwrap()))
+    }
+}
+
+/// Compute each pixel of a new image from the window of the specified image around it.
+///
+/// This function is a convient interface for the [`write_neighborhood_map`] function,
+/// automatically generating a new allocation in which to store the result.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{neighborhood_map, Border, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(3, 1, vec![1.0, 2.0, 6.0]).unwrap();
+///
+/// // The variance of each 3x1 window
+/// let variance = neighborhood_map(&image, (3, 1), Border::Replicate, |window| {
+///     let mean = window.iter().sum::<f64>() / 3.0;
+///     window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 3.0
+/// });
+/// assert!((variance.get_data()[0] - 2.0 / 9.0).abs() < 1e-12);
+/// ```
+#[cfg(feature = "std")]
+pub fn neighborhood_map<T, O>(
+    image: &impl Matrix<T>,
+    size: (usize, usize),
+    border: Border<T>,
+    f: impl Fn(&Neighborhood<'_, T>) -> O + MaybeSync,
+) -> DynamicMatrix<O>
+where
+    T: MaybeSync,
+    O: Default + Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let mut out = DynamicMatrix::new(
+        image.get_width(),
+        image.get_height(),
+        vec![O::default(); allocation],
+    )
+    .unwrap();
+    write_neighborhood_map(image, size, border, f, &mut out);
+    out
+}
+
+/// Write the result of computing each pixel from the window of the specified image around it into
+/// the specified buffer.
+///
+/// For every pixel, `f` is called with a [`Neighborhood`] giving access to the `(width, height)`
+/// window of the image around it, and the value it returns replaces the pixel in `out`. The window
+/// is positioned in the same way as a kernel of the same size in
+/// [`write_convolution`](crate::write_convolution), so for odd sizes the pixel being computed
+/// sits in the center. Pixels beyond the edges of the image are filled in according to `border`.
+///
+/// Rows are computed in parallel when the `rayon` feature is enabled, so `f` may be called from
+/// several threads at once. This makes it easy to write custom nonlinear filters, such as local
+/// variance or entropy, without handling the edges of the image or parallelism.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_neighborhood_map, Border, StaticMatrix};
+/// let image = StaticMatrix::new(3, 3, [
+///     0, 1, 0,
+///     1, 1, 1,
+///     0, 1, 0,
+/// ]).unwrap();
+///
+/// // Count the set pixels around each pixel
+/// let mut output = StaticMatrix::new(3, 3, [0; 9]).unwrap();
+/// write_neighborhood_map(&image, (3, 3), Border::Constant(0), |w| w.iter().sum(), &mut output);
+/// assert_eq!(output.into_parts().2, [3, 4, 3, 4, 5, 4, 3, 4, 3]);
+/// ```
+///
+/// # Panics
+/// If the dimensions of `out` do not match those of `image`, or if the window is empty.
+pub fn write_neighborhood_map<T, O>(
+    image: &impl Matrix<T>,
+    size: (usize, usize),
+    border: Border<T>,
+    f: impl Fn(&Neighborhood<'_, T>) -> O + MaybeSync,
+    out: &mut impl MatrixMut<O>,
+) where
+    T: MaybeSync,
+    O: MaybeSend,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    assert!(
+        out.get_width() == width && out.get_height() == height,
+        "Output dimensions do not match the image"
+    );
+    assert!(size.0 > 0 && size.1 > 0, "Window must not be empty");
+    if width == 0 {
+        return;
+    }
+    let anchor = (
+        ((size.1 + 1) >> 1).saturating_sub(1),
+        ((size.0 + 1) >> 1).saturating_sub(1),
+    );
+    let data = image.get_data();
+    let border = &border;
+
+    #[cfg(not(feature = "rayon"))]
+    let rows = out.get_data_mut().chunks_mut(width);
+    #[cfg(feature = "rayon")]
+    let rows = out.get_data_mut().par_chunks_mut(width);
+
+    rows.enumerate().for_each(|(row, line)| {
+        for (col, x) in line.iter_mut().enumerate() {
+            let window = Neighborhood {
+                data,
+                image_size: (width, height),
+                size,
+                origin: (
+                    row as isize - anchor.0 as isize,
+                    col as isize - anchor.1 as isize,
+                ),
+                anchor,
+                border,
+            };
+            *x = f(&window);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{neighborhood_map, Border};
+    use crate::{DynamicMatrix, Matrix, StaticMatrix};
+    use std::prelude::v1::*;
+    use test_case::test_case;
+
+    #[test_case(Border::Constant(9), [9, 9, 1, 2, 3, 4, 9, 9]; "constant")]
+    #[test_case(Border::Replicate, [1, 1, 1, 2, 3, 4, 4, 4]; "replicate")]
+    #[test_case(Border::Reflect, [3, 2, 1, 2, 3, 4, 3, 2]; "reflect")]
+    #[test_case(Border::Wrap, [3, 4, 1, 2, 3, 4, 1, 2]; "wrap")]
+    fn borders(border: Border<i32>, expected: [i32; 8]) {
+        let image = StaticMatrix::new(4, 1, [1, 2, 3, 4]).unwrap();
+        // Read the whole window around the first and last pixels
+        let windows = neighborhood_map(&image, (5, 1), border, |w| {
+            w.iter().copied().collect::<Vec<_>>()
+        });
+        let first = &windows.get_data()[0];
+        let last = &windows.get_data()[3];
+        assert_eq!(first[..4], expected[..4]);
+        assert_eq!(last[1..], expected[4..]);
+    }
+
+    #[test]
+    fn reflect_single_pixel() {
+        let image = StaticMatrix::new(1, 1, [7]).unwrap();
+        let output = neighborhood_map(&image, (5, 5), Border::Reflect, |w| w.iter().sum::<i32>());
+        assert_eq!(output.get_data(), &[7 * 25]);
+    }
+
+    #[test]
+    fn position_and_center() {
+        let image = DynamicMatrix::new(3, 2, (0..6).collect()).unwrap();
+        let output = neighborhood_map(&image, (3, 3), Border::Replicate, |w| {
+            let (row, col) = w.position();
+            assert_eq!(*w.center(), (row * 3 + col) as i32);
+            assert_eq!(w.get(1, 1), Some(w.center()));
+            assert_eq!(w.get(3, 0), None);
+            *w.get(0, 2).unwrap()
+        });
+        assert_eq!(output.get_data(), &[1, 2, 2, 1, 2, 2]);
+    }
+
+    #[test]
+    fn even_window_matches_convolution_anchor() {
+        let image = DynamicMatrix::new(4, 1, vec![1, 2, 3, 4]).unwrap();
+        // A two wide window has its anchor on the left
+        let output = neighborhood_map(&image, (2, 1), Border::Constant(0), |w| {
+            w.iter().sum::<i32>()
+        });
+        assert_eq!(output.get_data(), &[3, 5, 7, 4]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Static analysis of the values that an integer convolution can produce.
+//!
+//! Knowing the range of values that a convolution can output allows an appropriate accumulator
+//! type to be chosen ahead of time, rather than discovering overflow at runtime. This can be used
+//! to decide between [`write_convolution`](crate::write_convolution) and
+//! [`write_convolution_saturating`](crate::write_convolution_saturating).
+
+use crate::Matrix;
+use core::ops::RangeInclusive;
+
+/// The exact range of values that a convolution with a given kernel can produce.
+///
+/// See [`output_range`] for details on how this range is calculated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputRange {
+    /// The smallest value the convolution can produce
+    pub min: i128,
+    /// The largest value the convolution can produce
+    pub max: i128,
+}
+
+impl OutputRange {
+    /// Check whether every value in this range can be represented by the type `A`.
+    ///
+    /// Since every partial sum computed by the convolution is also within this range, an
+    /// accumulator of type `A` will never overflow if this function returns `true`.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, output_range};
+    /// let range = output_range(&kernel::sobel::x::<i32>(), 0u8..=255).unwrap();
+    /// assert!(range.fits::<i16>());
+    /// assert!(!range.fits::<i8>());
+    /// assert!(!range.fits::<u16>());
+    /// ```
+    pub fn fits<A: TryFrom<i128>>(&self) -> bool {
+        A::try_from(self.min).is_ok() && A::try_from(self.max).is_ok()
+    }
+
+    /// Get the narrowest primitive integer type which can hold every value in this range.
+    ///
+    /// Unsigned types are preferred over signed types of the same width.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, output_range, Accumulator};
+    /// let range = output_range(&kernel::laplacian::full::<i32>(), 0u8..=255).unwrap();
+    /// assert_eq!(range.accumulator(), Accumulator::I16);
+    /// ```
+    pub fn accumulator(&self) -> Accumulator {
+        use Accumulator::*;
+        [U8, I8, U16, I16, U32, I32, U64, I64, U128]
+            .into_iter()
+            .find(|a| a.holds(self))
+            .unwrap_or(I128)
+    }
+}
+
+/// The primitive integer types which can be used to accumulate the result of a convolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Accumulator {
+    /// An unsigned 8-bit integer
+    U8,
+    /// A signed 8-bit integer
+    I8,
+    /// An unsigned 16-bit integer
+    U16,
+    /// A signed 16-bit integer
+    I16,
+    /// An unsigned 32-bit integer
+    U32,
+    /// A signed 32-bit integer
+    I32,
+    /// An unsigned 64-bit integer
+    U64,
+    /// A signed 64-bit integer
+    I64,
+    /// An unsigned 128-bit integer
+    U128,
+    /// A signed 128-bit integer
+    I128,
+}
+
+impl Accumulator {
+    /// Check whether every value in the provided range can be represented by this type.
+    pub fn holds(&self, range: &OutputRange) -> bool {
+        match self {
+            Self::U8 => range.fits::<u8>(),
+            Self::I8 => range.fits::<i8>(),
+            Self::U16 => range.fits::<u16>(),
+            Self::I16 => range.fits::<i16>(),
+            Self::U32 => range.fits::<u32>(),
+            Self::I32 => range.fits::<i32>(),
+            Self::U64 => range.fits::<u64>(),
+            Self::I64 => range.fits::<i64>(),
+            Self::U128 => range.fits::<u128>(),
+            Self::I128 => range.fits::<i128>(),
+        }
+    }
+}
+
+/// Calculate the exact minimum and maximum values that a convolution with the provided kernel can
+/// produce, given that every image value lies within `input`.
+///
+/// Each kernel value contributes the product of itself and one image value to the output.
+/// Positive kernel values contribute the most when paired with the largest input, and negative
+/// kernel values when paired with the smallest. As the convolution functions treat the area
+/// outside of the image as zero, a contribution of zero is also always possible.
+///
+/// Because every tap is able to contribute zero, every partial sum formed while accumulating the
+/// convolution also lies within the returned range. This means that an accumulator which can hold
+/// the returned range will never overflow, regardless of the order in which taps are applied.
+///
+/// Returns `None` if the range cannot be represented using `i128`.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, output_range, OutputRange, Accumulator};
+/// let range = output_range(&kernel::sobel::y::<i32>(), 0u8..=255).unwrap();
+/// assert_eq!(range, OutputRange { min: -1020, max: 1020 });
+/// assert_eq!(range.accumulator(), Accumulator::I16);
+/// ```
+pub fn output_range<T, K>(kernel: &impl Matrix<K>, input: RangeInclusive<T>) -> Option<OutputRange>
+where
+    T: Into<i128>,
+    K: Into<i128> + Clone,
+{
+    let (low, high) = input.into_inner();
+    let (low, high): (i128, i128) = (low.into(), high.into());
+
+    let mut min = 0i128;
+    let mut max = 0i128;
+    for weight in kernel.get_data() {
+        let weight: i128 = weight.clone().into();
+        let a = weight.checked_mul(low)?;
+        let b = weight.checked_mul(high)?;
+        min = min.checked_add(a.min(b).min(0))?;
+        max = max.checked_add(a.max(b).max(0))?;
+    }
+
+    Some(OutputRange { min, max })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{output_range, Accumulator, OutputRange};
+    use crate::{kernel, StaticMatrix};
+    use test_case::test_case;
+
+    #[test_case(kernel::sobel::x(), -1020, 1020; "sobel_x")]
+    #[test_case(kernel::laplacian::cross(), -1020, 1020; "laplacian_cross")]
+    #[test_case(kernel::laplacian::full(), -2040, 2040; "laplacian_full")]
+    fn u8_ranges(kernel: StaticMatrix<i32, 9>, min: i128, max: i128) {
+        let range = output_range(&kernel, 0u8..=255).unwrap();
+        assert_eq!(range, OutputRange { min, max });
+        assert_eq!(range.accumulator(), Accumulator::I16);
+    }
+
+    #[test]
+    fn positive_kernel() {
+        let kernel = StaticMatrix::new(3, 3, [1u8, 2, 1, 2, 4, 2, 1, 2, 1]).unwrap();
+        let range = output_range(&kernel, 0u8..=255).unwrap();
+        assert_eq!(range, OutputRange { min: 0, max: 4080 });
+        assert_eq!(range.accumulator(), Accumulator::U16);
+    }
+
+    #[test]
+    fn includes_zero_padding() {
+        let kernel = StaticMatrix::new(1, 1, [2]).unwrap();
+        let range = output_range(&kernel, 10i32..=20).unwrap();
+        assert_eq!(range, OutputRange { min: 0, max: 40 });
+    }
+
+    #[test]
+    fn overflow() {
+        let kernel = StaticMatrix::new(3, 1, [i64::MAX; 3]).unwrap();
+        assert_eq!(output_range(&kernel, 0..=i64::MAX), None);
+    }
+}
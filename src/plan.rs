@@ -0,0 +1,763 @@
+//! Definitions of the [`ConvolutionPlan`] and [`StrategyPlan`], which prepare a kernel once so that
+//! it can be applied to many images of the same size.
+
+use crate::convolution::{taps, update_buffer};
+use crate::fft::Fft;
+use crate::kernel::{self, default_anchor};
+use crate::strategy::{fft_length, symmetric_taps, to_floats};
+use crate::{Channels, Complex, DynamicMatrix, Matrix, MatrixMut, MaybeSend, MaybeSync, Strategy};
+use core::ops::{Add, Mul};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// The options used to prepare a [`StrategyPlan`].
+///
+/// By default the kernel is anchored in the same position as in
+/// [`write_convolution`](crate::write_convolution), and the [`Strategy`] is chosen automatically.
+///
+/// # Example
+/// ```
+/// use convolve2d::{PlanOptions, Strategy};
+/// let options = PlanOptions::new().with_anchor(0, 0).with_strategy(Strategy::Fft);
+/// assert_eq!(options.anchor(), Some((0, 0)));
+/// assert_eq!(options.strategy(), Some(Strategy::Fft));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PlanOptions {
+    /// The row and column of the kernel element placed over the output pixel
+    anchor: Option<(usize, usize)>,
+    /// The strategy to use, or `None` to choose one with [`Strategy::choose`]
+    strategy: Option<Strategy>,
+}
+
+impl PlanOptions {
+    /// Create the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `(row, col)` position of the kernel element that sits on top of the output pixel.
+    pub fn with_anchor(mut self, row: usize, col: usize) -> Self {
+        self.anchor = Some((row, col));
+        self
+    }
+
+    /// Use the provided strategy rather than choosing one automatically.
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Get the anchor, or `None` if the default anchor is used
+    pub fn anchor(&self) -> Option<(usize, usize)> {
+        self.anchor
+    }
+
+    /// Get the strategy, or `None` if it is chosen automatically
+    pub fn strategy(&self) -> Option<Strategy> {
+        self.strategy
+    }
+}
+
+/// A kernel which has been prepared for convolving images of a fixed size.
+///
+/// Each call to [`write_convolution`](crate::write_convolution) flips the kernel and works out
+/// where each of its values lands in the output. When the same kernel is applied to many images
+/// of the same size, such as the frames of a video, this work can be done once up front by
+/// building a `ConvolutionPlan`.
+///
+/// A `ConvolutionPlan` always applies the kernel directly, so it accepts the same pixel types as
+/// `write_convolution` and produces exactly the same result. To use the symmetric, separable, or
+/// FFT strategies, build a [`StrategyPlan`] instead.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, ConvolutionPlan, DynamicMatrix};
+/// let plan = ConvolutionPlan::new(&kernel::box_blur(3), 64, 64);
+///
+/// for frame in 0..3 {
+///     let image = DynamicMatrix::new(64, 64, vec![frame as f64; 64 * 64]).unwrap();
+///     let mut output = DynamicMatrix::new(64, 64, vec![0.0; 64 * 64]).unwrap();
+///     plan.execute(&image, &mut output);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConvolutionPlan<K> {
+    /// The number of columns in the images this plan applies to
+    width: usize,
+    /// The number of rows in the images this plan applies to
+    height: usize,
+    /// The dimensions of the kernel this plan was built from
+    kernel_size: (usize, usize),
+    /// The kernel values, along with the alignment at which each is applied
+    taps: Vec<(isize, K)>,
+}
+
+impl<K: Clone> ConvolutionPlan<K> {
+    /// Prepare the provided kernel for convolving images with the specified dimensions.
+    ///
+    /// The kernel is anchored in the same position as in
+    /// [`write_convolution`](crate::write_convolution).
+    ///
+    /// # Example
+    /// ```
+    /// use convolve2d::{kernel, ConvolutionPlan, DynamicMatrix};
+    /// let plan = ConvolutionPlan::new(&kernel::box_blur(3), 4, 4);
+    ///
+    /// let image = DynamicMatrix::new(4, 4, vec![1.0; 16]).unwrap();
+    /// let mut output = DynamicMatrix::new(4, 4, vec![0.0; 16]).unwrap();
+    /// plan.execute(&image, &mut output);
+    /// ```
+    ///
+    /// # Panics
+    /// If the kernel's `get_value` method does not return `Some` for all row and column values in
+    /// the ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+    pub fn new(kernel: &impl Matrix<K>, width: usize, height: usize) -> Self {
+        Self::direct(kernel, default_anchor(kernel), width, height)
+    }
+
+    /// Prepare the provided kernel for convolving images with the specified dimensions, with the
+    /// given `(row, col)` position of the kernel sitting on top of each output pixel.
+    ///
+    /// Returns `None` if the anchor lies outside of the kernel matrix.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{ConvolutionPlan, StaticMatrix};
+    /// let kernel = StaticMatrix::new(2, 2, [1, 1, 1, 1]).unwrap();
+    /// assert!(ConvolutionPlan::with_anchor(&kernel, (1, 1), 8, 8).is_some());
+    /// assert!(ConvolutionPlan::with_anchor(&kernel, (0, 2), 8, 8).is_none());
+    /// ```
+    ///
+    /// # Panics
+    /// If the kernel's `get_value` method does not return `Some` for all row and column values in
+    /// the ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+    pub fn with_anchor(
+        kernel: &impl Matrix<K>,
+        anchor: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        if anchor.0 >= kernel.get_height() || anchor.1 >= kernel.get_width() {
+            return None;
+        }
+        Some(Self::direct(kernel, anchor, width, height))
+    }
+
+    fn direct(
+        kernel: &impl Matrix<K>,
+        anchor: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            kernel_size: (kernel.get_height(), kernel.get_width()),
+            taps: taps(kernel, anchor, width).collect(),
+        }
+    }
+}
+
+impl<K> ConvolutionPlan<K> {
+    /// Get the width of the images this plan applies to
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the images this plan applies to
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the `(height, width)` of the kernel this plan was built from
+    pub fn kernel_size(&self) -> (usize, usize) {
+        self.kernel_size
+    }
+
+    /// Write the convolution of the provided image and the planned kernel into the specified
+    /// buffer.
+    ///
+    /// As with [`write_convolution`](crate::write_convolution), the result of the convolution is
+    /// added to the values already in `out`.
+    ///
+    /// # Example
+    /// ```
+    /// use convolve2d::{ConvolutionPlan, StaticMatrix};
+    /// let kernel = StaticMatrix::new(3, 1, [1, 0, -1]).unwrap();
+    /// let plan = ConvolutionPlan::new(&kernel, 3, 1);
+    ///
+    /// let image = StaticMatrix::new(3, 1, [1, 2, 3]).unwrap();
+    /// let mut output = StaticMatrix::new(3, 1, [0; 3]).unwrap();
+    /// plan.execute(&image, &mut output);
+    /// assert_eq!(output.into_parts().2, [-2, -2, 2]);
+    /// ```
+    ///
+    /// # Panics
+    /// If the dimensions of the image or the output buffer do not match those of the plan.
+    pub fn execute<T, O>(&self, image: &impl Matrix<T>, out: &mut impl MatrixMut<O>)
+    where
+        T: Mul<K, Output = O> + Clone + MaybeSend + MaybeSync,
+        K: Clone + MaybeSend + MaybeSync,
+        O: Add<Output = O> + Clone + MaybeSend,
+    {
+        assert!(
+            image.get_width() == self.width && image.get_height() == self.height,
+            "Image dimensions do not match the plan"
+        );
+        assert!(
+            out.get_width() == self.width && out.get_height() == self.height,
+            "Output dimensions do not match the plan"
+        );
+
+        for (alignment, value) in &self.taps {
+            // Apply this kernel value and add to the buffer
+            update_buffer(
+                image.get_data(),
+                value.clone(),
+                *alignment,
+                out.get_data_mut(),
+            );
+        }
+    }
+}
+
+/// A kernel which has been prepared for convolving images of a fixed size using a chosen
+/// [`Strategy`].
+///
+/// The symmetric strategy caches the pairs of mirrored kernel values, the separable strategy
+/// caches the factors of the kernel, and the FFT strategy caches the transform of the kernel. Each
+/// keeps the scratch memory it needs between executions, so executing the plan again does not
+/// allocate.
+///
+/// Executing a [`Strategy::Direct`] plan produces exactly the same result as a
+/// [`ConvolutionPlan`]. The other strategies work on each of the pixel's [`Channels`] as an `f64`,
+/// so they give the same result up to floating point rounding error, and integer outputs are
+/// rounded to the nearest value. Since every strategy may be chosen, the pixel types must
+/// implement `Channels`. Use a `ConvolutionPlan` for pixel types which do not.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, DynamicMatrix, PlanOptions, Strategy, StrategyPlan};
+/// let kernel = kernel::gaussian(41, 7.0);
+/// let mut plan = StrategyPlan::with_options(&kernel, 64, 64, PlanOptions::new()).unwrap();
+/// assert_eq!(plan.strategy(), Strategy::Separable);
+///
+/// for frame in 0..3 {
+///     let image = DynamicMatrix::new(64, 64, vec![frame as f64; 64 * 64]).unwrap();
+///     let mut output = DynamicMatrix::new(64, 64, vec![0.0; 64 * 64]).unwrap();
+///     plan.execute(&image, &mut output);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StrategyPlan<K> {
+    /// The number of columns in the images this plan applies to
+    width: usize,
+    /// The number of rows in the images this plan applies to
+    height: usize,
+    /// The dimensions of the kernel this plan was built from
+    kernel_size: (usize, usize),
+    /// The data needed by the chosen strategy
+    prepared: Prepared<K>,
+    /// The memory reused by each execution of the plan
+    scratch: Scratch,
+}
+
+/// The precomputed data for each strategy.
+#[derive(Debug, Clone)]
+enum Prepared<K> {
+    Direct(ConvolutionPlan<K>),
+    Symmetric {
+        /// The alignments of each pair of mirrored values, and the value they share
+        pairs: Vec<(isize, isize, f64)>,
+        /// The alignments and values of the kernel values which lie on the mirror
+        singles: Vec<(isize, f64)>,
+        /// Whether the second value of each pair is the negation of the first
+        antisymmetric: bool,
+    },
+    Separable {
+        /// The alignments and values of the horizontal pass
+        row: Vec<(isize, f64)>,
+        /// The alignments and values of the vertical pass
+        column: Vec<(isize, f64)>,
+        /// The index of the first element of the intermediate buffer in the image
+        start: isize,
+    },
+    Fft {
+        fft: Fft,
+        /// The transform of the kernel
+        spectrum: Vec<Complex<f64>>,
+        /// The smallest alignment of any kernel value
+        offset: isize,
+    },
+}
+
+/// The buffers used by the strategies which work on each channel of the image as an `f64`.
+#[derive(Debug, Clone, Default)]
+struct Scratch {
+    /// A single channel of the image
+    channel: Vec<f64>,
+    /// The result of the horizontal pass of the separable strategy
+    intermediate: Vec<f64>,
+    /// The buffer in which the image is transformed by the FFT strategy
+    transform: Vec<Complex<f64>>,
+    /// The result for each channel, one after another
+    result: Vec<f64>,
+}
+
+impl<K: Clone + Into<f64>> StrategyPlan<K> {
+    /// Prepare the provided kernel for convolving images with the specified dimensions, with the
+    /// provided options.
+    ///
+    /// Returns `None` if the anchor lies outside of the kernel matrix, or if
+    /// [`Strategy::Symmetric`] or [`Strategy::Separable`] is requested for a kernel which is not
+    /// symmetric or separable.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, PlanOptions, Strategy, StrategyPlan};
+    /// let fft = PlanOptions::new().with_strategy(Strategy::Fft);
+    /// let plan = StrategyPlan::with_options(&kernel::gaussian(5, 1.0), 64, 64, fft);
+    /// assert_eq!(plan.unwrap().strategy(), Strategy::Fft);
+    ///
+    /// let separable = PlanOptions::new().with_strategy(Strategy::Separable);
+    /// let laplacian = kernel::laplacian::full::<f64>();
+    /// assert!(StrategyPlan::with_options(&laplacian, 64, 64, separable).is_none());
+    /// ```
+    ///
+    /// # Panics
+    /// If the kernel's `get_value` method does not return `Some` for all row and column values in
+    /// the ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+    pub fn with_options(
+        kernel: &impl Matrix<K>,
+        width: usize,
+        height: usize,
+        options: PlanOptions,
+    ) -> Option<Self> {
+        let anchor = options.anchor.unwrap_or_else(|| default_anchor(kernel));
+        if anchor.0 >= kernel.get_height() || anchor.1 >= kernel.get_width() {
+            return None;
+        }
+
+        let floats = to_floats(kernel);
+
+        let separable = match options.strategy {
+            None | Some(Strategy::Separable) => kernel::separate(&floats),
+            _ => None,
+        };
+        let strategy = options
+            .strategy
+            .unwrap_or_else(|| Strategy::choose_with(&floats, width, height, separable.as_ref()));
+
+        let mut scratch = Scratch::default();
+        let prepared = match strategy {
+            Strategy::Direct => {
+                Prepared::Direct(ConvolutionPlan::direct(kernel, anchor, width, height))
+            }
+            Strategy::Symmetric => {
+                let symmetry = kernel::symmetry(&floats)?;
+                let (pairs, singles) = symmetric_taps(&floats, symmetry, anchor, width);
+                Prepared::Symmetric {
+                    pairs,
+                    singles,
+                    antisymmetric: symmetry.antisymmetric,
+                }
+            }
+            Strategy::Separable => {
+                let (column, row) = separable?;
+                let column = DynamicMatrix::new(1, column.len(), column).unwrap();
+                let row = DynamicMatrix::new(row.len(), 1, row).unwrap();
+                let column: Vec<_> = taps(&column, (anchor.0, 0), width).collect();
+                let row: Vec<_> = taps(&row, (0, anchor.1), width).collect();
+
+                // The horizontal pass is needed wherever the vertical pass will read from it,
+                // which can extend past either end of the image
+                let alignments = || column.iter().map(|(a, _)| *a);
+                let start = -alignments().max().unwrap_or(0);
+                let end = (width * height) as isize - alignments().min().unwrap_or(0);
+                scratch.intermediate = vec![0.0; (end - start).max(0) as usize];
+                Prepared::Separable { row, column, start }
+            }
+            Strategy::Fft => {
+                let taps: Vec<_> = taps(&floats, anchor, width).collect();
+                let offset = taps.iter().map(|(a, _)| *a).min().unwrap_or(0);
+                let len = fft_length(&floats, width, height).unwrap_or(1);
+
+                let fft = Fft::new(len);
+                let mut spectrum = vec![Complex::default(); len];
+                for (alignment, value) in taps {
+                    // Kernels wider than the image can have several values at the same alignment
+                    let index = (alignment - offset) as usize;
+                    spectrum[index] = spectrum[index] + Complex::new(value, 0.0);
+                }
+                fft.forward(&mut spectrum);
+                scratch.transform = vec![Complex::default(); len];
+
+                Prepared::Fft {
+                    fft,
+                    spectrum,
+                    offset,
+                }
+            }
+        };
+
+        Some(Self {
+            width,
+            height,
+            kernel_size: (kernel.get_height(), kernel.get_width()),
+            prepared,
+            scratch,
+        })
+    }
+}
+
+impl<K> StrategyPlan<K> {
+    /// Get the width of the images this plan applies to
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the images this plan applies to
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the `(height, width)` of the kernel this plan was built from
+    pub fn kernel_size(&self) -> (usize, usize) {
+        self.kernel_size
+    }
+
+    /// Get the strategy used by this plan
+    pub fn strategy(&self) -> Strategy {
+        match self.prepared {
+            Prepared::Direct(_) => Strategy::Direct,
+            Prepared::Symmetric { .. } => Strategy::Symmetric,
+            Prepared::Separable { .. } => Strategy::Separable,
+            Prepared::Fft { .. } => Strategy::Fft,
+        }
+    }
+
+    /// Write the convolution of the provided image and the planned kernel into the specified
+    /// buffer.
+    ///
+    /// As with [`write_convolution`](crate::write_convolution), the result of the convolution is
+    /// added to the values already in `out`.
+    ///
+    /// # Example
+    /// ```
+    /// use convolve2d::{PlanOptions, StaticMatrix, Strategy, StrategyPlan};
+    /// let kernel = StaticMatrix::new(3, 1, [1, 2, 1]).unwrap();
+    /// let options = PlanOptions::new().with_strategy(Strategy::Symmetric);
+    /// let mut plan = StrategyPlan::with_options(&kernel, 3, 1, options).unwrap();
+    ///
+    /// let image = StaticMatrix::new(3, 1, [1, 2, 3]).unwrap();
+    /// let mut output = StaticMatrix::new(3, 1, [0; 3]).unwrap();
+    /// plan.execute(&image, &mut output);
+    /// assert_eq!(output.into_parts().2, [4, 8, 8]);
+    /// ```
+    ///
+    /// # Panics
+    /// If the dimensions of the image or the output buffer do not match those of the plan, or if
+    /// a strategy other than [`Strategy::Direct`] is used and the image and output have a
+    /// different number of channels.
+    pub fn execute<T, O>(&mut self, image: &impl Matrix<T>, out: &mut impl MatrixMut<O>)
+    where
+        T: Mul<K, Output = O> + Channels + Clone + MaybeSend + MaybeSync,
+        K: Clone + MaybeSend + MaybeSync,
+        O: Add<Output = O> + Channels + Clone + MaybeSend,
+    {
+        if let Prepared::Direct(plan) = &self.prepared {
+            plan.execute(image, out);
+            return;
+        }
+
+        assert!(
+            image.get_width() == self.width && image.get_height() == self.height,
+            "Image dimensions do not match the plan"
+        );
+        assert!(
+            out.get_width() == self.width && out.get_height() == self.height,
+            "Output dimensions do not match the plan"
+        );
+        assert_eq!(
+            T::CHANNELS,
+            O::CHANNELS,
+            "Image and output have a different number of channels"
+        );
+        let pixels = self.width * self.height;
+        if pixels == 0 {
+            return;
+        }
+
+        let Scratch {
+            channel,
+            intermediate,
+            transform,
+            result,
+        } = &mut self.scratch;
+        result.clear();
+        result.resize(pixels * T::CHANNELS, 0.0);
+
+        for (index, result) in result.chunks_mut(pixels).enumerate() {
+            channel.clear();
+            channel.extend(image.get_data().iter().map(|x| x.channel(index)));
+
+            match &self.prepared {
+                Prepared::Direct(_) => unreachable!(),
+                Prepared::Symmetric {
+                    pairs,
+                    singles,
+                    antisymmetric,
+                } => {
+                    accumulate(channel, 0, singles, result, 0);
+                    accumulate_pairs(channel, pairs, *antisymmetric, result);
+                }
+                Prepared::Separable { row, column, start } => {
+                    // Apply the horizontal pass, then the vertical pass to its result
+                    intermediate.iter_mut().for_each(|x| *x = 0.0);
+                    accumulate(channel, 0, row, intermediate, *start);
+                    accumulate(intermediate, *start, column, result, 0);
+                }
+                Prepared::Fft {
+                    fft,
+                    spectrum,
+                    offset,
+                } => {
+                    let values = channel.iter().map(|x| Complex::new(*x, 0.0));
+                    let zeros = core::iter::repeat(Complex::default());
+                    for (s, x) in transform.iter_mut().zip(values.chain(zeros)) {
+                        *s = x;
+                    }
+
+                    fft.forward(transform);
+                    transform
+                        .iter_mut()
+                        .zip(spectrum.iter())
+                        .for_each(|(s, k)| *s = *s * *k);
+                    fft.inverse(transform);
+
+                    for (i, a) in result.iter_mut().enumerate() {
+                        if let Some(x) = usize::try_from(i as isize - *offset)
+                            .ok()
+                            .and_then(|i| transform.get(i))
+                        {
+                            *a = x.re;
+                        }
+                    }
+                }
+            }
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        let buf_iter = out.get_data_mut().iter_mut();
+        #[cfg(feature = "rayon")]
+        let buf_iter = out.get_data_mut().par_iter_mut();
+
+        let result = &*result;
+        buf_iter.enumerate().for_each(|(i, a)| {
+            *a = a.clone() + O::from_channels(|index| result[index * pixels + i]);
+        });
+    }
+}
+
+/// Add the contribution of each tap to the output buffer, where `input_start` and `out_start` are
+/// the positions of the first element of each buffer in the flattened image.
+fn accumulate(
+    input: &[f64],
+    input_start: isize,
+    taps: &[(isize, f64)],
+    out: &mut [f64],
+    out_start: isize,
+) {
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = out.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = out.par_iter_mut();
+
+    buf_iter.enumerate().for_each(|(i, a)| {
+        for (alignment, value) in taps {
+            let source = i as isize + out_start - alignment - input_start;
+            if let Some(x) = usize::try_from(source).ok().and_then(|i| input.get(i)) {
+                *a += x * value;
+            }
+        }
+    });
+}
+
+/// Add the contribution of each pair of mirrored kernel values to the output buffer, adding (or
+/// subtracting) the two image values before multiplying them by the value they share.
+fn accumulate_pairs(
+    input: &[f64],
+    pairs: &[(isize, isize, f64)],
+    antisymmetric: bool,
+    out: &mut [f64],
+) {
+    let sample = |i: usize, alignment: isize| {
+        usize::try_from(i as isize - alignment)
+            .ok()
+            .and_then(|i| input.get(i))
+            .copied()
+            .unwrap_or(0.0)
+    };
+
+    #[cfg(not(feature = "rayon"))]
+    let buf_iter = out.iter_mut();
+    #[cfg(feature = "rayon")]
+    let buf_iter = out.par_iter_mut();
+
+    buf_iter.enumerate().for_each(|(i, a)| {
+        for (first, second, value) in pairs {
+            let (x, y) = (sample(i, *first), sample(i, *second));
+            *a += if antisymmetric { x - y } else { x + y } * value;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConvolutionPlan, PlanOptions, StrategyPlan};
+    use crate::SubPixels;
+    use crate::{kernel, write_convolution, DynamicMatrix, Matrix, StaticMatrix, Strategy};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    #[test]
+    fn matches_write_convolution() {
+        let kernel = StaticMatrix::new(3, 2, [1, -2, 0, 3, 4, -1]).unwrap();
+        let plan = ConvolutionPlan::new(&kernel, 4, 3);
+
+        for frame in 0..3 {
+            let data = (0..12).map(|i| i * frame - 5).collect();
+            let image = DynamicMatrix::new(4, 3, data).unwrap();
+
+            let mut expected = DynamicMatrix::new(4, 3, vec![0; 12]).unwrap();
+            let mut output = DynamicMatrix::new(4, 3, vec![0; 12]).unwrap();
+            write_convolution(&image, &kernel, &mut expected);
+            plan.execute(&image, &mut output);
+
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn custom_anchor() {
+        let kernel = StaticMatrix::new(2, 1, [1, 10]).unwrap();
+        let plan = ConvolutionPlan::with_anchor(&kernel, (0, 1), 3, 1).unwrap();
+
+        let image = StaticMatrix::new(3, 1, [1, 2, 3]).unwrap();
+        let mut output = StaticMatrix::new(3, 1, [0; 3]).unwrap();
+        plan.execute(&image, &mut output);
+
+        assert_eq!(output.into_parts().2, [10, 21, 32]);
+    }
+
+    #[test]
+    fn direct_i64() {
+        // Direct plans never convert to `f64`, so they stay exact beyond `2^53`
+        let kernel = StaticMatrix::new(3, 3, [1i64, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+        let data = (0..20).map(|i| (i * 7 % 13 - 6) << 50).collect();
+        let image = DynamicMatrix::new(5, 4, data).unwrap();
+        let plan = ConvolutionPlan::new(&kernel, 5, 4);
+
+        let mut expected = DynamicMatrix::new(5, 4, vec![0i64; 20]).unwrap();
+        let mut output = DynamicMatrix::new(5, 4, vec![0i64; 20]).unwrap();
+        write_convolution(&image, &kernel, &mut expected);
+        plan.execute(&image, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test_case(Strategy::Direct; "direct")]
+    #[test_case(Strategy::Separable; "separable")]
+    #[test_case(Strategy::Fft; "fft")]
+    fn options_anchor(strategy: Strategy) {
+        let kernel = StaticMatrix::new(3, 1, [1.0, 2.0, 4.0]).unwrap();
+        let options = PlanOptions::new().with_anchor(0, 2).with_strategy(strategy);
+        let mut plan = StrategyPlan::with_options(&kernel, 4, 1, options).unwrap();
+        assert_eq!(plan.strategy(), strategy);
+
+        let image = StaticMatrix::new(4, 1, [1.0, 2.0, 3.0, 4.0]).unwrap();
+        let mut output = StaticMatrix::new(4, 1, [0.0; 4]).unwrap();
+        plan.execute(&image, &mut output);
+        for (a, b) in output.get_data().iter().zip([4.0f64, 10.0, 17.0, 24.0]) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+
+        let options = PlanOptions::new().with_anchor(1, 0);
+        assert!(StrategyPlan::with_options(&kernel, 4, 1, options).is_none());
+    }
+
+    #[test]
+    fn symmetric_anchor() {
+        let kernel = StaticMatrix::new(3, 1, [1.0, 2.0, 1.0]).unwrap();
+        let image = StaticMatrix::new(4, 1, [1.0, -2.0, 3.0, 5.0]).unwrap();
+        let mut expected = StaticMatrix::new(4, 1, [0.0; 4]).unwrap();
+        let mut output = StaticMatrix::new(4, 1, [0.0; 4]).unwrap();
+
+        let options = PlanOptions::new().with_anchor(0, 2);
+        ConvolutionPlan::with_anchor(&kernel, (0, 2), 4, 1)
+            .unwrap()
+            .execute(&image, &mut expected);
+        let mut plan =
+            StrategyPlan::with_options(&kernel, 4, 1, options.with_strategy(Strategy::Symmetric))
+                .unwrap();
+        plan.execute(&image, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test_case(Strategy::Symmetric; "symmetric")]
+    #[test_case(Strategy::Separable; "separable")]
+    #[test_case(Strategy::Fft; "fft")]
+    fn scratch_is_reused(strategy: Strategy) {
+        let kernel = kernel::gaussian(5, 1.0);
+        let options = PlanOptions::new().with_strategy(strategy);
+        let mut plan = StrategyPlan::with_options(&kernel, 6, 5, options).unwrap();
+
+        // Executing the plan again must not see anything left over from the previous image
+        for frame in [3.0, -1.0, 0.5] {
+            let data = (0..30).map(|i| frame * (i % 4) as f64).collect();
+            let image = DynamicMatrix::new(6, 5, data).unwrap();
+            let mut expected = DynamicMatrix::new(6, 5, vec![0.0; 30]).unwrap();
+            let mut output = DynamicMatrix::new(6, 5, vec![0.0; 30]).unwrap();
+            write_convolution(&image, &kernel, &mut expected);
+            plan.execute(&image, &mut output);
+            for (a, b) in output.get_data().iter().zip(expected.get_data()) {
+                assert!((a - b).abs() < 1e-9, "{a} != {b}");
+            }
+        }
+    }
+
+    #[test_case(Strategy::Symmetric; "symmetric")]
+    #[test_case(Strategy::Separable; "separable")]
+    #[test_case(Strategy::Fft; "fft")]
+    fn channels_are_rounded(strategy: Strategy) {
+        let kernel = kernel::sobel::x::<i32>();
+        let options = PlanOptions::new().with_strategy(strategy);
+        let mut plan = StrategyPlan::with_options(&kernel, 5, 4, options).unwrap();
+
+        let data = (0..20)
+            .map(|i| SubPixels([i * 7 % 13, i * 3 % 5 - 2]))
+            .collect();
+        let image = DynamicMatrix::new(5, 4, data).unwrap();
+        let mut expected = DynamicMatrix::new(5, 4, vec![SubPixels([0; 2]); 20]).unwrap();
+        let mut output = DynamicMatrix::new(5, 4, vec![SubPixels([0; 2]); 20]).unwrap();
+        write_convolution(&image, &kernel, &mut expected);
+        plan.execute(&image, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_image_size() {
+        let plan = ConvolutionPlan::new(&kernel::box_blur(3), 4, 4);
+        let image = DynamicMatrix::new(3, 3, vec![0.0; 9]).unwrap();
+        let mut output = DynamicMatrix::new(4, 4, vec![0.0; 16]).unwrap();
+        plan.execute(&image, &mut output);
+    }
+}
@@ -0,0 +1,745 @@
+//! Median and other rank-order filters.
+
+use crate::{DynamicMatrix, Matrix, MaybeSend, MaybeSync, SubPixels};
+use core::cmp::Ordering;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// The number of output columns each thread handles at once in the histogram algorithm.
+const STRIP_WIDTH: usize = 64;
+
+/// A trait for types which can be sorted within a window, for use with [`rank_filter`],
+/// [`percentile_filter`], and [`median_filter`].
+///
+/// This trait is implemented for the primitive numeric types, using the fastest algorithm
+/// available for each: `u8` and `u16` images are filtered using histograms, while other types are
+/// sorted. Floating point values are ordered using [`f64::total_cmp`]. [`SubPixels`] are filtered
+/// one channel at a time.
+///
+/// Requires the `std` feature.
+pub trait RankFilter: Sized {
+    /// Replace each value of a `width` by `height` image with the value of the specified rank
+    /// within the window around it.
+    ///
+    /// The window extends `horizontal_radius` columns to either side of each pixel, and
+    /// `vertical_radius` rows above and below it, and `rank` is always less than the number of
+    /// values in the window.
+    fn filter_by_rank(
+        image: &[Self],
+        width: usize,
+        height: usize,
+        horizontal_radius: usize,
+        vertical_radius: usize,
+        rank: usize,
+    ) -> Vec<Self>;
+}
+
+/// Replace each pixel of the specified image with the value of the specified rank within a
+/// rectangular window around it.
+///
+/// The window extends `horizontal_radius` columns to either side of each pixel, and
+/// `vertical_radius` rows above and below it. A `rank` of zero selects the smallest value in the
+/// window, and a `rank` of one less than the number of values in the window selects the largest.
+/// Pixels beyond the edges of the image are taken to be copies of the nearest pixel on the edge,
+/// so every window is full.
+///
+/// This differs from [`min_filter`](crate::min_filter), [`max_filter`](crate::max_filter), and
+/// the morphological operations such as [`dilate`](crate::dilate), which ignore the parts of the
+/// window outside of the image. The nearest edge pixel always lies in the window, so the smallest
+/// and largest ranks give the same result either way, but the ranks in between, including the
+/// median, count each edge pixel once for every copy of it.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{rank_filter, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(4, 1, vec![3u8, 1, 4, 1]).unwrap();
+///
+/// let output = rank_filter(&image, 1, 0, 2);
+/// assert_eq!(output.get_data(), &[3, 4, 4, 4]);
+/// ```
+///
+/// # Panics
+/// If `rank` is not less than the number of values in the window.
+pub fn rank_filter<T: RankFilter>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+    rank: usize,
+) -> DynamicMatrix<T> {
+    let window = (2 * horizontal_radius + 1) * (2 * vertical_radius + 1);
+    assert!(
+        rank < window,
+        "Rank must be less than the size of the window"
+    );
+
+    let (width, height) = (image.get_width(), image.get_height());
+    let data = T::filter_by_rank(
+        image.get_data(),
+        width,
+        height,
+        horizontal_radius,
+        vertical_radius,
+        rank,
+    );
+    DynamicMatrix::new(width, height, data).unwrap()
+}
+
+/// Replace each pixel of the specified image with the given percentile of a rectangular window
+/// around it.
+///
+/// A `percentile` of `0.0` selects the smallest value in the window, `50.0` the median, and
+/// `100.0` the largest, rounding to the nearest rank. See [`rank_filter`] for details of the
+/// window.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{percentile_filter, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(3, 3, vec![
+///     1, 2, 3,
+///     4, 5, 6,
+///     7, 8, 9,
+/// ]).unwrap();
+///
+/// let output = percentile_filter(&image, 1, 1, 0.0);
+/// assert_eq!(output.get_data(), &[1, 1, 2, 1, 1, 2, 4, 4, 5]);
+/// ```
+///
+/// # Panics
+/// If `percentile` is not in the range `0.0..=100.0`.
+pub fn percentile_filter<T: RankFilter>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+    percentile: f64,
+) -> DynamicMatrix<T> {
+    assert!(
+        (0.0..=100.0).contains(&percentile),
+        "Percentile must be between 0 and 100"
+    );
+    let window = (2 * horizontal_radius + 1) * (2 * vertical_radius + 1);
+    let rank = (percentile / 100.0 * (window - 1) as f64).round() as usize;
+    rank_filter(image, horizontal_radius, vertical_radius, rank)
+}
+
+/// Replace each pixel of the specified image with the median of a rectangular window around it.
+///
+/// Median filters remove salt-and-pepper noise, in which isolated pixels are set to extreme
+/// values, while keeping edges sharp. See [`rank_filter`] for details of the window.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{median_filter, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(3, 3, vec![
+///     10, 10, 10,
+///     10, 255, 10,
+///     10, 10, 0,
+/// ]).unwrap();
+///
+/// let output = median_filter(&image, 1, 1);
+/// assert_eq!(output.get_data(), &[10u8; 9]);
+/// ```
+pub fn median_filter<T: RankFilter>(
+    image: &impl Matrix<T>,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+) -> DynamicMatrix<T> {
+    let window = (2 * horizontal_radius + 1) * (2 * vertical_radius + 1);
+    rank_filter(image, horizontal_radius, vertical_radius, window / 2)
+}
+
+macro_rules! histogram_rank_impl {
+    ($($t:ty),+) => {
+        $(
+            impl RankFilter for $t {
+                fn filter_by_rank(
+                    image: &[Self],
+                    width: usize,
+                    height: usize,
+                    horizontal_radius: usize,
+                    vertical_radius: usize,
+                    rank: usize,
+                ) -> Vec<Self> {
+                    let window = Window::new(width, height, horizontal_radius, vertical_radius);
+                    if window.is_small() {
+                        network_filter(image, window, rank, |a: &$t, b: &$t| a < b)
+                    } else {
+                        histogram_filter(
+                            image,
+                            window,
+                            rank,
+                            <$t>::BITS,
+                            |x: $t| x as usize,
+                            |bin| bin as $t,
+                        )
+                    }
+                }
+            }
+        )+
+    };
+}
+
+histogram_rank_impl!(u8, u16);
+
+macro_rules! sorting_rank_impl {
+    ($($t:ty),+) => {
+        $(
+            impl RankFilter for $t {
+                fn filter_by_rank(
+                    image: &[Self],
+                    width: usize,
+                    height: usize,
+                    horizontal_radius: usize,
+                    vertical_radius: usize,
+                    rank: usize,
+                ) -> Vec<Self> {
+                    let window = Window::new(width, height, horizontal_radius, vertical_radius);
+                    if window.is_small() {
+                        network_filter(image, window, rank, |a: &$t, b: &$t| a < b)
+                    } else {
+                        select_filter(image, window, rank, <$t>::cmp)
+                    }
+                }
+            }
+        )+
+    };
+}
+
+sorting_rank_impl!(u32, u64, u128, usize);
+sorting_rank_impl!(i8, i16, i32, i64, i128, isize);
+
+macro_rules! float_rank_impl {
+    ($($t:ty),+) => {
+        $(
+            impl RankFilter for $t {
+                fn filter_by_rank(
+                    image: &[Self],
+                    width: usize,
+                    height: usize,
+                    horizontal_radius: usize,
+                    vertical_radius: usize,
+                    rank: usize,
+                ) -> Vec<Self> {
+                    let window = Window::new(width, height, horizontal_radius, vertical_radius);
+                    if window.is_small() {
+                        network_filter(image, window, rank, |a: &$t, b: &$t| a.total_cmp(b).is_lt())
+                    } else {
+                        select_filter(image, window, rank, <$t>::total_cmp)
+                    }
+                }
+            }
+        )+
+    };
+}
+
+float_rank_impl!(f32, f64);
+
+impl<T: RankFilter + Copy, const N: usize> RankFilter for SubPixels<T, N> {
+    fn filter_by_rank(
+        image: &[Self],
+        width: usize,
+        height: usize,
+        horizontal_radius: usize,
+        vertical_radius: usize,
+        rank: usize,
+    ) -> Vec<Self> {
+        let channels: Vec<Vec<T>> = (0..N)
+            .map(|i| {
+                let channel: Vec<T> = image.iter().map(|x| x.0[i]).collect();
+                T::filter_by_rank(
+                    &channel,
+                    width,
+                    height,
+                    horizontal_radius,
+                    vertical_radius,
+                    rank,
+                )
+            })
+            .collect();
+        (0..image.len())
+            .map(|j| SubPixels(core::array::from_fn(|i| channels[i][j])))
+            .collect()
+    }
+}
+
+/// The dimensions of an image, and of the window used to filter it.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    width: usize,
+    height: usize,
+    horizontal_radius: usize,
+    vertical_radius: usize,
+}
+
+impl Window {
+    fn new(width: usize, height: usize, horizontal_radius: usize, vertical_radius: usize) -> Self {
+        Self {
+            width,
+            height,
+            horizontal_radius,
+            vertical_radius,
+        }
+    }
+
+    /// Check whether the window is 3x3 or 5x5, which are sorted with a sorting network
+    fn is_small(&self) -> bool {
+        self.horizontal_radius == self.vertical_radius && matches!(self.horizontal_radius, 1 | 2)
+    }
+
+    /// Get the number of values in the window
+    fn len(&self) -> usize {
+        (2 * self.horizontal_radius + 1) * (2 * self.vertical_radius + 1)
+    }
+
+    /// Copy the window around the pixel at `(row, col)` into `buf`, repeating the pixels on the
+    /// edges of the image as needed.
+    fn gather<T: Copy>(&self, image: &[T], row: usize, col: usize, buf: &mut [T]) {
+        let mut i = 0;
+        for dr in 0..=2 * self.vertical_radius {
+            let r = clamp(row + dr, self.vertical_radius, self.height);
+            for dc in 0..=2 * self.horizontal_radius {
+                let c = clamp(col + dc, self.horizontal_radius, self.width);
+                buf[i] = image[r * self.width + c];
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Find the index of a row or column which is `radius` before `index`, clamped to the image.
+fn clamp(index: usize, radius: usize, len: usize) -> usize {
+    index.saturating_sub(radius).min(len - 1)
+}
+
+/// Filter an image by sorting each window with a sorting network.
+fn network_filter<T: Copy + MaybeSend + MaybeSync>(
+    image: &[T],
+    window: Window,
+    rank: usize,
+    less: fn(&T, &T) -> bool,
+) -> Vec<T> {
+    let mut out = image.to_vec();
+    if image.is_empty() {
+        return out;
+    }
+    let network = sorting_network(window.len());
+
+    #[cfg(not(feature = "rayon"))]
+    let rows = out.chunks_mut(window.width);
+    #[cfg(feature = "rayon")]
+    let rows = out.par_chunks_mut(window.width);
+
+    rows.enumerate().for_each(|(row, line)| {
+        let mut buf = [image[0]; 25];
+        let buf = &mut buf[..window.len()];
+        for (col, x) in line.iter_mut().enumerate() {
+            window.gather(image, row, col, buf);
+            for &(i, j) in &network {
+                if less(&buf[j], &buf[i]) {
+                    buf.swap(i, j);
+                }
+            }
+            *x = buf[rank];
+        }
+    });
+    out
+}
+
+/// Generate Batcher's odd-even merge sorting network for `n` values, as a list of pairs of
+/// indices to compare and swap.
+fn sorting_network(n: usize) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    let mut p = 1;
+    while p < n {
+        let mut k = p;
+        while k >= 1 {
+            let mut j = k % p;
+            while j + k < n {
+                for i in 0..k.min(n - j - k) {
+                    if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                        pairs.push((i + j, i + j + k));
+                    }
+                }
+                j += 2 * k;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+    pairs
+}
+
+/// Filter an image by partially sorting each window.
+fn select_filter<T: Copy + MaybeSend + MaybeSync>(
+    image: &[T],
+    window: Window,
+    rank: usize,
+    cmp: fn(&T, &T) -> Ordering,
+) -> Vec<T> {
+    let mut out = image.to_vec();
+    if image.is_empty() {
+        return out;
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    let rows = out.chunks_mut(window.width);
+    #[cfg(feature = "rayon")]
+    let rows = out.par_chunks_mut(window.width);
+
+    rows.enumerate().for_each(|(row, line)| {
+        let mut buf = vec![image[0]; window.len()];
+        for (col, x) in line.iter_mut().enumerate() {
+            window.gather(image, row, col, &mut buf);
+            *x = *buf.select_nth_unstable_by(rank, cmp).1;
+        }
+    });
+    out
+}
+
+/// Filter an image of `bits` bit values using the constant time median filtering algorithm of
+/// Perreault and Hébert.
+///
+/// A histogram of each column of the window is kept up to date as the window moves down the
+/// image, so that the histogram of the whole window can be updated by adding one column and
+/// removing another as it moves across. Each histogram is split into a coarse level, which counts
+/// the values with the same upper half of their bits, and a fine level, so that a value of any
+/// rank can be found quickly.
+fn histogram_filter<T: Copy + MaybeSend + MaybeSync>(
+    image: &[T],
+    window: Window,
+    rank: usize,
+    bits: u32,
+    bin: fn(T) -> usize,
+    value: fn(usize) -> T,
+) -> Vec<T> {
+    assert!(
+        2 * window.vertical_radius < u16::MAX as usize,
+        "Window is too tall"
+    );
+    let mut out = image.to_vec();
+    if image.is_empty() {
+        return out;
+    }
+
+    let strips: Vec<_> = (0..window.width).step_by(STRIP_WIDTH).collect();
+
+    #[cfg(not(feature = "rayon"))]
+    let strip_iter = strips.into_iter();
+    #[cfg(feature = "rayon")]
+    let strip_iter = strips.into_par_iter();
+
+    let results: Vec<_> = strip_iter
+        .map(|start| {
+            let end = (start + STRIP_WIDTH).min(window.width);
+            let strip = histogram_strip(image, window, rank, bits, bin, value, start, end);
+            (start, end, strip)
+        })
+        .collect();
+
+    for (start, end, strip) in results {
+        for (row, line) in strip.chunks(end - start).enumerate() {
+            let offset = row * window.width + start;
+            out[offset..offset + line.len()].copy_from_slice(line);
+        }
+    }
+    out
+}
+
+/// Filter the columns `start..end` of an image using histograms.
+#[allow(clippy::too_many_arguments)]
+fn histogram_strip<T: Copy>(
+    image: &[T],
+    window: Window,
+    rank: usize,
+    bits: u32,
+    bin: fn(T) -> usize,
+    value: fn(usize) -> T,
+    start: usize,
+    end: usize,
+) -> Vec<T> {
+    let Window {
+        width,
+        height,
+        horizontal_radius,
+        vertical_radius,
+    } = window;
+    let fine_bits = bits / 2;
+    let segment = 1 << fine_bits;
+    let (coarse_len, fine_len) = (1 << (bits - fine_bits), 1 << bits);
+    let diameter = 2 * horizontal_radius + 1;
+
+    // The histograms of each column which the window passes over, including those past the edges
+    let columns = end - start + 2 * horizontal_radius;
+    let source = |i: usize| clamp(start + i, horizontal_radius, width);
+    let mut column_coarse = vec![0u16; columns * coarse_len];
+    let mut column_fine = vec![0u16; columns * fine_len];
+    let update_column = |coarse: &mut [u16], fine: &mut [u16], i: usize, row: usize, add: bool| {
+        let b = bin(image[row * width + source(i)]);
+        let (coarse, fine) = (
+            &mut coarse[i * coarse_len + (b >> fine_bits)],
+            &mut fine[i * fine_len + b],
+        );
+        if add {
+            *coarse += 1;
+            *fine += 1;
+        } else {
+            *coarse -= 1;
+            *fine -= 1;
+        }
+    };
+    for i in 0..columns {
+        for dr in 0..=2 * vertical_radius {
+            update_column(
+                &mut column_coarse,
+                &mut column_fine,
+                i,
+                clamp(dr, vertical_radius, height),
+                true,
+            );
+        }
+    }
+
+    let mut out = Vec::with_capacity((end - start) * height);
+    let mut coarse = vec![0u32; coarse_len];
+    let mut fine = vec![0u32; fine_len];
+    // The position of the window when each segment of the fine histogram was last brought up to
+    // date, as the fine histogram is only updated for the segments which are needed
+    let mut fine_at = vec![None; coarse_len];
+
+    for row in 0..height {
+        if row > 0 {
+            for i in 0..columns {
+                let (leave, enter) = (row - 1, row + 2 * vertical_radius);
+                let (coarse, fine) = (&mut column_coarse, &mut column_fine);
+                update_column(
+                    coarse,
+                    fine,
+                    i,
+                    clamp(leave, vertical_radius, height),
+                    false,
+                );
+                update_column(coarse, fine, i, clamp(enter, vertical_radius, height), true);
+            }
+        }
+
+        coarse.fill(0);
+        fine_at.fill(None);
+        for i in 0..diameter {
+            add_counts(
+                &mut coarse,
+                &column_coarse[i * coarse_len..][..coarse_len],
+                true,
+            );
+        }
+
+        for x in 0..end - start {
+            // The window covers the columns x..x + diameter
+            if x > 0 {
+                let (enter, leave) = (x + diameter - 1, x - 1);
+                add_counts(
+                    &mut coarse,
+                    &column_coarse[enter * coarse_len..][..coarse_len],
+                    true,
+                );
+                add_counts(
+                    &mut coarse,
+                    &column_coarse[leave * coarse_len..][..coarse_len],
+                    false,
+                );
+            }
+
+            let mut remaining = rank as u32;
+            let mut b = 0;
+            while coarse[b] <= remaining {
+                remaining -= coarse[b];
+                b += 1;
+            }
+
+            let range = b * segment..(b + 1) * segment;
+            let column_segment = |i: usize| &column_fine[i * fine_len..][range.clone()];
+            match fine_at[b] {
+                Some(previous) if x - previous <= horizontal_radius => {
+                    for q in previous + 1..=x {
+                        add_counts(
+                            &mut fine[range.clone()],
+                            column_segment(q + diameter - 1),
+                            true,
+                        );
+                        add_counts(&mut fine[range.clone()], column_segment(q - 1), false);
+                    }
+                }
+                _ => {
+                    fine[range.clone()].fill(0);
+                    for i in x..x + diameter {
+                        add_counts(&mut fine[range.clone()], column_segment(i), true);
+                    }
+                }
+            }
+            fine_at[b] = Some(x);
+
+            let mut f = range.start;
+            while fine[f] <= remaining {
+                remaining -= fine[f];
+                f += 1;
+            }
+            out.push(value(f));
+        }
+    }
+    out
+}
+
+/// Add or subtract the counts of one histogram to or from another.
+fn add_counts(total: &mut [u32], counts: &[u16], add: bool) {
+    for (t, &c) in total.iter_mut().zip(counts) {
+        if add {
+            *t += c as u32;
+        } else {
+            *t -= c as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{median_filter, rank_filter, sorting_network, RankFilter, Window};
+    use crate::{max_filter, min_filter, DynamicMatrix, Matrix, SubPixels};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    /// Filter an image by sorting every window in full
+    fn reference<T: Copy + Ord>(
+        image: &DynamicMatrix<T>,
+        hr: usize,
+        vr: usize,
+        rank: usize,
+    ) -> Vec<T> {
+        let (width, height) = (image.get_width(), image.get_height());
+        let window = Window::new(width, height, hr, vr);
+        let mut buf = vec![image.get_data()[0]; window.len()];
+        let mut out = vec![];
+        for row in 0..height {
+            for col in 0..width {
+                window.gather(image.get_data(), row, col, &mut buf);
+                buf.sort();
+                out.push(buf[rank]);
+            }
+        }
+        out
+    }
+
+    fn image<T: TryFrom<u32>>(width: usize, height: usize, modulus: u32) -> DynamicMatrix<T> {
+        let data = (0..(width * height) as u32)
+            .map(|i| {
+                T::try_from(i.wrapping_mul(2654435761) % modulus)
+                    .ok()
+                    .unwrap()
+            })
+            .collect();
+        DynamicMatrix::new(width, height, data).unwrap()
+    }
+
+    #[test_case(9)]
+    #[test_case(25)]
+    #[test_case(7)]
+    fn sorting_network_sorts(n: usize) {
+        let network = sorting_network(n);
+        for seed in 0..200u32 {
+            let mut values: Vec<u32> = (0..n as u32)
+                .map(|i| (i + seed).wrapping_mul(2654435761) % 13)
+                .collect();
+            let mut expected = values.clone();
+            expected.sort();
+            for &(i, j) in &network {
+                if values[j] < values[i] {
+                    values.swap(i, j);
+                }
+            }
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test_case(1, 1, 4; "network_3x3")]
+    #[test_case(2, 2, 0; "network_5x5")]
+    #[test_case(3, 1, 10; "histogram_wide")]
+    #[test_case(0, 4, 8; "histogram_tall")]
+    #[test_case(5, 5, 120; "histogram_large")]
+    fn u8_matches_reference(hr: usize, vr: usize, rank: usize) {
+        let image = image::<u8>(70, 9, 256);
+        let output = rank_filter(&image, hr, vr, rank);
+        assert_eq!(output.get_data(), &reference(&image, hr, vr, rank)[..]);
+    }
+
+    #[test_case(1, 2, 7)]
+    #[test_case(4, 3, 31)]
+    fn u16_matches_reference(hr: usize, vr: usize, rank: usize) {
+        let image = image::<u16>(67, 8, 65536);
+        let output = rank_filter(&image, hr, vr, rank);
+        assert_eq!(output.get_data(), &reference(&image, hr, vr, rank)[..]);
+    }
+
+    #[test_case(1, 1)]
+    #[test_case(3, 2)]
+    fn i32_matches_reference(hr: usize, vr: usize) {
+        let image = image::<i32>(11, 7, 1000);
+        let window = (2 * hr + 1) * (2 * vr + 1);
+        let output = median_filter(&image, hr, vr);
+        assert_eq!(
+            output.get_data(),
+            &reference(&image, hr, vr, window / 2)[..]
+        );
+    }
+
+    #[test]
+    fn floats() {
+        let image = DynamicMatrix::new(3, 1, vec![0.5, -1.0, 2.0]).unwrap();
+        assert_eq!(median_filter(&image, 1, 0).get_data(), &[0.5, 0.5, 2.0]);
+    }
+
+    #[test]
+    fn subpixels_per_channel() {
+        let image = DynamicMatrix::new(
+            3,
+            1,
+            vec![SubPixels([1u8, 9]), SubPixels([5, 3]), SubPixels([2, 4])],
+        )
+        .unwrap();
+        let output = median_filter(&image, 1, 0);
+        assert_eq!(
+            output.get_data(),
+            &[SubPixels([1, 9]), SubPixels([2, 4]), SubPixels([2, 4])]
+        );
+    }
+
+    #[test]
+    fn single_pixel() {
+        let output = u8::filter_by_rank(&[7], 1, 1, 3, 3, 20);
+        assert_eq!(output, vec![7]);
+    }
+
+    #[test]
+    fn edges_are_replicated() {
+        let image = image::<u8>(9, 7, 256);
+        assert_eq!(rank_filter(&image, 2, 1, 0), min_filter(&image, 2, 1));
+        assert_eq!(rank_filter(&image, 2, 1, 14), max_filter(&image, 2, 1));
+
+        // The corner's window holds three copies of the corner, so it is the median
+        let image = DynamicMatrix::new(2, 2, vec![0u8, 9, 9, 9]).unwrap();
+        assert_eq!(median_filter(&image, 1, 1).get_data()[0], 9);
+        let image = DynamicMatrix::new(2, 2, vec![9u8, 0, 0, 0]).unwrap();
+        assert_eq!(median_filter(&image, 1, 1).get_data()[0], 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rank_too_large() {
+        rank_filter(&image::<u8>(4, 4, 256), 1, 1, 9);
+    }
+}
@@ -0,0 +1,392 @@
+//! Convolutions over semirings other than the ordinary sum of products, such as the max-plus and
+//! min-plus semirings used by mathematical morphology.
+
+use crate::kernel::Tap;
+use crate::matrix::{Matrix, MatrixMut};
+use crate::{Lattice, MaybeSend, MaybeSync, SaturatingAdd};
+use core::ops::{Add, Mul};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// Re-import the standard library
+#[cfg(feature = "std")]
+use crate::DynamicMatrix;
+#[cfg(feature = "std")]
+use std::prelude::v1::*;
+
+/// A pair of operations which take the place of multiplication and addition in a convolution.
+///
+/// An ordinary convolution multiplies each image value by a kernel value, and adds the products
+/// together. A semiring convolution instead combines each image value with a kernel value using
+/// [`mul`](Semiring::mul), and combines the results using [`add`](Semiring::add), starting from
+/// [`zero`](Semiring::zero). Replacing the sum of products with the maximum of sums, for example,
+/// gives the grayscale dilation used in mathematical morphology.
+///
+/// The library provides the [`SumProduct`], [`MaxPlus`], and [`MinPlus`] semirings, and others
+/// can be used with [`write_convolution_semiring`] by implementing this trait.
+///
+/// Semiring convolutions do not use the same machinery as
+/// [`write_convolution`](crate::write_convolution), and handle the edges of the image
+/// differently. Taps which fall outside of the image are skipped, as though the image were
+/// surrounded by the semiring's zero, rather than wrapping onto the neighbouring row. The
+/// morphological operations, such as [`dilate`](crate::dilate), share this behaviour.
+pub trait Semiring<T, K> {
+    /// The type of the convolution's output
+    type Output;
+
+    /// The identity of `add`, which every output value starts from
+    fn zero(&self) -> Self::Output;
+
+    /// Combine two partial results
+    fn add(&self, lhs: Self::Output, rhs: Self::Output) -> Self::Output;
+
+    /// Combine an image value with a kernel value
+    fn mul(&self, value: &T, weight: &K) -> Self::Output;
+}
+
+/// The ordinary semiring of addition and multiplication.
+///
+/// Convolving over this semiring gives the same result as
+/// [`write_convolution`](crate::write_convolution) with the equivalent kernel wherever the kernel
+/// lies entirely within the image. Near the edges, it matches the convolution of the image
+/// surrounded by zeros, while `write_convolution` wraps onto the neighbouring row.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{Semiring, SumProduct};
+/// assert_eq!(Semiring::<i32, i32>::add(&SumProduct, 2, 3), 5);
+/// assert_eq!(SumProduct.mul(&2, &3), 6);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SumProduct;
+
+impl<T, K, O> Semiring<T, K> for SumProduct
+where
+    T: Mul<K, Output = O> + Clone,
+    K: Clone,
+    O: Add<Output = O> + Default,
+{
+    type Output = O;
+
+    fn zero(&self) -> O {
+        O::default()
+    }
+
+    fn add(&self, lhs: O, rhs: O) -> O {
+        lhs + rhs
+    }
+
+    fn mul(&self, value: &T, weight: &K) -> O {
+        value.clone() * weight.clone()
+    }
+}
+
+/// The semiring in which the maximum takes the place of addition, and addition takes the place of
+/// multiplication.
+///
+/// The addition saturates, so that integer images are clamped to the range of their type rather
+/// than overflowing.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{MaxPlus, Semiring};
+/// assert_eq!(Semiring::<i32, i32>::add(&MaxPlus, 2, 3), 3);
+/// assert_eq!(MaxPlus.mul(&2, &3), 5);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MaxPlus;
+
+impl<T, K, O> Semiring<T, K> for MaxPlus
+where
+    T: SaturatingAdd<K, Output = O> + Clone,
+    K: Clone,
+    O: Lattice,
+{
+    type Output = O;
+
+    fn zero(&self) -> O {
+        O::bottom()
+    }
+
+    fn add(&self, lhs: O, rhs: O) -> O {
+        lhs.sup(rhs)
+    }
+
+    fn mul(&self, value: &T, weight: &K) -> O {
+        value.clone().saturating_add(weight.clone())
+    }
+}
+
+/// The semiring in which the minimum takes the place of addition, and addition takes the place of
+/// multiplication.
+///
+/// The addition saturates, so that integer images are clamped to the range of their type rather
+/// than overflowing.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{MinPlus, Semiring};
+/// assert_eq!(Semiring::<i32, i32>::add(&MinPlus, 2, 3), 2);
+/// assert_eq!(MinPlus.mul(&2, &3), 5);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MinPlus;
+
+impl<T, K, O> Semiring<T, K> for MinPlus
+where
+    T: SaturatingAdd<K, Output = O> + Clone,
+    K: Clone,
+    O: Lattice,
+{
+    type Output = O;
+
+    fn zero(&self) -> O {
+        O::top()
+    }
+
+    fn add(&self, lhs: O, rhs: O) -> O {
+        lhs.inf(rhs)
+    }
+
+    fn mul(&self, value: &T, weight: &K) -> O {
+        value.clone().saturating_add(weight.clone())
+    }
+}
+
+/// Perform a convolution of the specified image with the provided sparse kernel, over the given
+/// semiring.
+///
+/// This function is a convient interface for the [`write_convolution_semiring`] function,
+/// automatically generating a new allocation, filled with the semiring's zero, in which to store
+/// the convolution.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d_semiring, DynamicMatrix, Matrix, MaxPlus, Tap};
+/// let image = DynamicMatrix::new(4, 1, vec![1, 5, 2, 0]).unwrap();
+/// let taps = [Tap::new(0, -1, 0), Tap::new(0, 0, 0), Tap::new(0, 1, 0)];
+///
+/// let output: DynamicMatrix<i32> = convolve2d_semiring(&image, &taps, &MaxPlus);
+/// assert_eq!(output.get_data(), &[5, 5, 5, 2]);
+/// ```
+#[cfg(feature = "std")]
+pub fn convolve2d_semiring<T, K, S>(
+    image: &impl Matrix<T>,
+    taps: &[Tap<K>],
+    semiring: &S,
+) -> DynamicMatrix<S::Output>
+where
+    T: MaybeSync,
+    K: MaybeSync,
+    S: Semiring<T, K> + MaybeSync,
+    S::Output: Clone + MaybeSend,
+{
+    let allocation = image.get_width() * image.get_height();
+    let data = (0..allocation).map(|_| semiring.zero()).collect();
+    let mut out = DynamicMatrix::new(image.get_width(), image.get_height(), data).unwrap();
+    write_convolution_semiring(image, taps, semiring, &mut out);
+    out
+}
+
+/// Write the convolution of the provided image and sparse kernel over the given semiring into the
+/// specified buffer.
+///
+/// For every output pixel at `(r, c)`, each [`Tap`] combines the image value at
+/// `(r + tap.row, c + tap.col)` with its weight using [`Semiring::mul`], and the result is
+/// combined with the value already in `out` using [`Semiring::add`]. When `out` is filled with
+/// the semiring's [`zero`](Semiring::zero), this gives the convolution of the image and kernel.
+///
+/// Unlike [`write_convolution`](crate::write_convolution), taps which fall outside of the image
+/// are skipped, rather than wrapping onto the neighbouring row. This is equivalent to surrounding
+/// the image with the semiring's zero, which is what morphological operations such as
+/// [`dilate`](crate::dilate) expect.
+///
+/// # Example
+/// ```
+/// use convolve2d::{write_convolution_semiring, MinPlus, StaticMatrix, Tap};
+/// let image = StaticMatrix::new(3, 2, [
+///     4, 2, 7,
+///     1, 9, 3,
+/// ]).unwrap();
+/// let taps = [Tap::new(0, 0, 0), Tap::new(1, 0, 0)];
+///
+/// let mut output = StaticMatrix::new(3, 2, [i32::MAX; 6]).unwrap();
+/// write_convolution_semiring(&image, &taps, &MinPlus, &mut output);
+/// assert_eq!(output.into_parts().2, [1, 2, 3, 1, 9, 3]);
+/// ```
+///
+/// # Panics
+/// If the dimensions of `out` do not match those of `image`.
+pub fn write_convolution_semiring<T, K, S>(
+    image: &impl Matrix<T>,
+    taps: &[Tap<K>],
+    semiring: &S,
+    out: &mut impl MatrixMut<S::Output>,
+) where
+    T: MaybeSync,
+    K: MaybeSync,
+    S: Semiring<T, K> + MaybeSync,
+    S::Output: Clone + MaybeSend,
+{
+    accumulate_taps(image, taps, false, out, |acc, value, weight| {
+        semiring.add(acc, semiring.mul(value, weight))
+    });
+}
+
+/// Combine each tap's image values into the output buffer, skipping taps that fall outside of the
+/// image.
+///
+/// If `reflected` is set, each tap is mirrored through the anchor, reading the image value at
+/// `(r - tap.row, c - tap.col)` instead.
+///
+/// # Panics
+/// If the dimensions of `out` do not match those of `image`.
+pub(crate) fn accumulate_taps<T, K, O>(
+    image: &impl Matrix<T>,
+    taps: &[Tap<K>],
+    reflected: bool,
+    out: &mut impl MatrixMut<O>,
+    combine: impl Fn(O, &T, &K) -> O + MaybeSync,
+) where
+    T: MaybeSync,
+    K: MaybeSync,
+    O: Clone + MaybeSend,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    assert!(
+        out.get_width() == width && out.get_height() == height,
+        "Output dimensions do not match the image"
+    );
+    if width == 0 {
+        return;
+    }
+    let data = image.get_data();
+
+    #[cfg(not(feature = "rayon"))]
+    let rows = out.get_data_mut().chunks_mut(width);
+    #[cfg(feature = "rayon")]
+    let rows = out.get_data_mut().par_chunks_mut(width);
+
+    rows.enumerate().for_each(|(row, line)| {
+        for tap in taps {
+            let (tap_row, tap_col) = match reflected {
+                false => (tap.row, tap.col),
+                true => (-tap.row, -tap.col),
+            };
+            let source = row as isize + tap_row;
+            if source < 0 || source >= height as isize {
+                continue;
+            }
+            let source = &data[source as usize * width..][..width];
+
+            // Only the columns for which the tap lands inside the image are updated
+            let first = (-tap_col).clamp(0, width as isize) as usize;
+            let last = (width as isize - tap_col).clamp(0, width as isize) as usize;
+            for col in first..last {
+                let value = &source[(col as isize + tap_col) as usize];
+                line[col] = combine(line[col].clone(), value, &tap.weight);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convolve2d_semiring, MaxPlus, MinPlus, SumProduct};
+    use crate::{convolve2d, kernel, DynamicMatrix, Matrix, StaticMatrix, SubPixels, Tap};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    #[test_case(0, 0, [1, 2, 3, 4, 5, 6]; "identity")]
+    #[test_case(0, 1, [2, 3, 0, 5, 6, 0]; "right")]
+    #[test_case(-1, 0, [0, 0, 0, 1, 2, 3]; "up")]
+    #[test_case(1, -1, [0, 4, 5, 0, 0, 0]; "down_left")]
+    fn sum_product_does_not_wrap(row: isize, col: isize, expected: [i32; 6]) {
+        let image = StaticMatrix::new(3, 2, [1, 2, 3, 4, 5, 6]).unwrap();
+        let output: DynamicMatrix<i32> =
+            convolve2d_semiring(&image, &[Tap::new(row, col, 1)], &SumProduct);
+        assert_eq!(output.get_data(), &expected);
+    }
+
+    #[test]
+    fn sum_product_matches_zero_padded_convolution() {
+        let image = DynamicMatrix::new(5, 4, (0..20).map(|i| (i * 7) % 9).collect()).unwrap();
+        let kernel = StaticMatrix::new(3, 3, [1, 0, -2, 3, 1, 0, 0, 4, -1]).unwrap();
+        let output: DynamicMatrix<i32> =
+            convolve2d_semiring(&image, &kernel::sparse(&kernel), &SumProduct);
+
+        // Inside the image, where no taps fall off the edge, it is the ordinary convolution
+        let direct = convolve2d(&image, &kernel);
+        for row in 1..3 {
+            for col in 1..4 {
+                let i = row * 5 + col;
+                assert_eq!(output.get_data()[i], direct.get_data()[i]);
+            }
+        }
+
+        // Everywhere, it is the ordinary convolution of the image surrounded by zeros
+        let padded = (0..7 * 6)
+            .map(|i| {
+                let (row, col) = (i / 7, i % 7);
+                match (row, col) {
+                    (1..=4, 1..=5) => image.get_data()[(row - 1) * 5 + col - 1],
+                    _ => 0,
+                }
+            })
+            .collect();
+        let padded = convolve2d(&DynamicMatrix::new(7, 6, padded).unwrap(), &kernel);
+        let cropped: Vec<i32> = padded
+            .get_data()
+            .chunks(7)
+            .skip(1)
+            .take(4)
+            .flat_map(|row| row[1..6].iter().copied())
+            .collect();
+        assert_eq!(output.get_data(), &cropped[..]);
+    }
+
+    #[test]
+    fn max_plus_with_weights() {
+        let image = DynamicMatrix::new(3, 1, vec![1.0, 4.0, 2.0]).unwrap();
+        let taps = [
+            Tap::new(0, -1, -1.0),
+            Tap::new(0, 0, 0.0),
+            Tap::new(0, 1, -1.0),
+        ];
+        let output = convolve2d_semiring(&image, &taps, &MaxPlus);
+        assert_eq!(output.get_data(), &[3.0, 4.0, 3.0]);
+    }
+
+    #[test]
+    fn plus_saturates() {
+        let image = DynamicMatrix::new(3, 1, vec![0u8, 250, 5]).unwrap();
+        let taps = [Tap::new(0, 0, 10u8)];
+        let output = convolve2d_semiring(&image, &taps, &MaxPlus);
+        assert_eq!(output.get_data(), &[10, 255, 15]);
+        let output = convolve2d_semiring(&image, &taps, &MinPlus);
+        assert_eq!(output.get_data(), &[10, 255, 15]);
+    }
+
+    #[test]
+    fn no_taps_gives_zero() {
+        let image = DynamicMatrix::new(2, 2, vec![1u8, 2, 3, 4]).unwrap();
+        let output = convolve2d_semiring(&image, &[] as &[Tap<u8>], &MinPlus);
+        assert_eq!(output.get_data(), &[u8::MAX; 4]);
+    }
+
+    #[test]
+    fn subpixels_per_channel() {
+        let image = DynamicMatrix::new(
+            2,
+            1,
+            vec![SubPixels::<u8, 2>([1, 9]), SubPixels::<u8, 2>([5, 3])],
+        )
+        .unwrap();
+        let taps = [Tap::new(0, 0, 0u8), Tap::new(0, 1, 0u8)];
+        let output = convolve2d_semiring(&image, &taps, &MaxPlus);
+        assert_eq!(output.get_data(), &[SubPixels([5, 9]), SubPixels([5, 3])]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Sharpening by unsharp masking.
+
+use crate::morphology::transpose;
+use crate::{convolve1d, kernel, Channels, DynamicMatrix, Matrix, MaybeSend, MaybeSync, Mode};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+
+/// Sharpen the specified image by unsharp masking.
+///
+/// The image is blurred with a Gaussian of standard deviation `sigma`, and the difference between
+/// the image and its blur, which holds the image's fine details, is scaled by `amount` and added
+/// back to the image. Differences smaller than `threshold` are left alone, so that noise in flat
+/// areas of the image is not amplified.
+///
+/// Each of the pixel's [`Channels`] is sharpened independently, and the result is converted back
+/// to the pixel type, so integer images are rounded and clamped to the range of their type rather
+/// than wrapping around. The blur replicates the pixels at the edges of the image.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{unsharp_mask, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(8, 1, vec![10u8, 10, 10, 10, 250, 250, 250, 250]).unwrap();
+///
+/// // The edge overshoots on both sides, clamped to the range of a `u8`
+/// let output = unsharp_mask(&image, 1.0, 4.0, 0.0);
+/// assert_eq!(output.get_data()[3], 0);
+/// assert_eq!(output.get_data()[4], 255);
+///
+/// // Pixels far from the edge are left alone
+/// assert_eq!(output.get_data()[0], 10);
+/// ```
+///
+/// # Panics
+/// If `sigma` is not positive.
+pub fn unsharp_mask<T>(
+    image: &impl Matrix<T>,
+    sigma: f64,
+    amount: f64,
+    threshold: f64,
+) -> DynamicMatrix<T>
+where
+    T: Channels + MaybeSend + MaybeSync,
+{
+    assert!(sigma > 0.0, "Sigma must be positive");
+    let (width, height) = (image.get_width(), image.get_height());
+    let data = image.get_data();
+
+    let details = (0..T::CHANNELS)
+        .map(|index| {
+            let channel: Vec<f64> = data.iter().map(|x| x.channel(index)).collect();
+            let blurred = gaussian_blur(&channel, width, sigma);
+            channel
+                .iter()
+                .zip(blurred)
+                .map(|(x, blurred)| {
+                    let detail = x - blurred;
+                    if detail.abs() < threshold {
+                        *x
+                    } else {
+                        x + amount * detail
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let pixels = (0..data.len())
+        .map(|i| T::from_channels(|index| details[index][i]))
+        .collect();
+    DynamicMatrix::new(width, height, pixels).unwrap()
+}
+
+/// Sharpen the specified image by high-boost filtering.
+///
+/// The image is scaled by `boost` and its Gaussian blur, with standard deviation `sigma`, is
+/// subtracted from it. This is the same as [`unsharp_mask`] with an `amount` of `boost - 1` and no
+/// threshold, so a `boost` of 1 leaves the image unchanged and larger values sharpen it further.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{high_boost, DynamicMatrix, Matrix};
+/// let image = DynamicMatrix::new(4, 1, vec![0.0, 0.0, 1.0, 1.0]).unwrap();
+///
+/// let output = high_boost(&image, 1.0, 2.0);
+/// assert!(output.get_data()[1] < 0.0);
+/// assert!(output.get_data()[2] > 1.0);
+/// ```
+///
+/// # Panics
+/// If `sigma` is not positive.
+pub fn high_boost<T>(image: &impl Matrix<T>, sigma: f64, boost: f64) -> DynamicMatrix<T>
+where
+    T: Channels + MaybeSend + MaybeSync,
+{
+    unsharp_mask(image, sigma, boost - 1.0, 0.0)
+}
+
+/// Get the weights of a one dimensional Gaussian, covering three standard deviations on each side.
+///
+/// These are the sums of the rows of [`kernel::gaussian`], which is separable, and so are the
+/// weights that blur each column of an image in the same way as the full kernel.
+fn gaussian_weights(sigma: f64) -> Vec<f64> {
+    let size = 2 * (3.0 * sigma).ceil() as usize + 1;
+    kernel::gaussian(size, sigma)
+        .get_data()
+        .chunks(size)
+        .map(|row| row.iter().sum())
+        .collect()
+}
+
+/// Blur a single channel of an image with rows of `width` values with a Gaussian of standard
+/// deviation `sigma`, replicating its edges.
+pub(crate) fn gaussian_blur(data: &[f64], width: usize, sigma: f64) -> Vec<f64> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let weights = &gaussian_weights(sigma);
+    let height = data.len() / width;
+    let rows = blur_lines(data, width, weights);
+    let columns = blur_lines(&transpose(&rows, width), height, weights);
+    transpose(&columns, height)
+}
+
+/// Blur each line of `len` values in `data`, replicating the values at either end of the line.
+fn blur_lines(data: &[f64], len: usize, weights: &[f64]) -> Vec<f64> {
+    let radius = weights.len() / 2;
+
+    #[cfg(not(feature = "rayon"))]
+    let lines = data.chunks(len);
+    #[cfg(feature = "rayon")]
+    let lines = data.par_chunks(len);
+
+    lines
+        .map(|line| {
+            let padded: Vec<f64> = (0..len + 2 * radius)
+                .map(|i| line[i.saturating_sub(radius).min(len - 1)])
+                .collect();
+            convolve1d::<_, _, f64>(&padded, weights, Mode::Valid)
+        })
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gaussian_weights, high_boost, unsharp_mask};
+    use crate::{DynamicMatrix, Matrix, SubPixels};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    #[test_case(0.5; "narrow")]
+    #[test_case(1.0; "unit")]
+    #[test_case(2.5; "wide")]
+    fn weights_are_normalized(sigma: f64) {
+        let weights = gaussian_weights(sigma);
+        assert_eq!(weights.len() % 2, 1);
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn flat_image_is_unchanged() {
+        let image = DynamicMatrix::new(5, 4, vec![0.25; 20]).unwrap();
+        let output = unsharp_mask(&image, 1.5, 3.0, 0.0);
+        for x in output.get_data().iter() {
+            assert!((x - 0.25f64).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn matches_manual_subtraction() {
+        let data: Vec<f64> = (0..7 * 6).map(|i| ((i * 37) % 11) as f64).collect();
+        let image = DynamicMatrix::new(7, 6, data.clone()).unwrap();
+        let sharpened = unsharp_mask(&image, 1.0, 0.5, 0.0);
+        // An amount of -1 replaces each pixel with its blur
+        let blurred = unsharp_mask(&image, 1.0, -1.0, 0.0);
+        for ((x, s), b) in data
+            .iter()
+            .zip(sharpened.get_data())
+            .zip(blurred.get_data())
+        {
+            assert!((s - (x + 0.5 * (x - b))).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn threshold_ignores_small_details() {
+        let image = DynamicMatrix::new(5, 1, vec![100u16, 101, 100, 101, 100]).unwrap();
+        assert_eq!(
+            unsharp_mask(&image, 1.0, 10.0, 2.0).get_data(),
+            image.get_data()
+        );
+        assert_ne!(
+            unsharp_mask(&image, 1.0, 10.0, 0.0).get_data(),
+            image.get_data()
+        );
+    }
+
+    #[test]
+    fn subpixels_clamp_each_channel() {
+        let image = DynamicMatrix::new(
+            4,
+            1,
+            vec![
+                SubPixels([5u8, 128, 250]),
+                SubPixels([5, 128, 250]),
+                SubPixels([250, 128, 5]),
+                SubPixels([250, 128, 5]),
+            ],
+        )
+        .unwrap();
+        let output = unsharp_mask(&image, 1.0, 5.0, 0.0);
+        assert_eq!(output.get_data()[1], SubPixels([0, 128, 255]));
+        assert_eq!(output.get_data()[2], SubPixels([255, 128, 0]));
+    }
+
+    #[test]
+    fn unit_boost_is_identity() {
+        let image = DynamicMatrix::new(3, 2, vec![1.0f32, 4.0, 2.0, 8.0, 5.0, 7.0]).unwrap();
+        let output = high_boost(&image, 0.8, 1.0);
+        assert_eq!(output.get_data(), image.get_data());
+    }
+
+    #[test]
+    fn empty_image() {
+        let image = DynamicMatrix::<u8>::new(0, 0, vec![]).unwrap();
+        assert!(unsharp_mask(&image, 1.0, 1.0, 0.0).get_data().is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+//! Automatic selection between the different ways of computing a convolution.
+//!
+//! Small kernels are fastest to apply directly, but the cost of a direct convolution grows with
+//! the number of kernel values. Symmetric kernels can share a multiplication between each pair of
+//! mirrored values, separable kernels, such as a gaussian blur, can instead be applied as a
+//! vertical pass followed by a horizontal pass, and large dense kernels are cheapest to apply in
+//! the frequency domain using a fast Fourier transform. A [`StrategyPlan`] uses a simple cost
+//! model to pick between these [`Strategy`]s.
+
+use crate::convolution::taps;
+use crate::kernel::{self, default_anchor, Symmetry};
+use crate::{Channels, DynamicMatrix, Matrix, MaybeSend, MaybeSync, PlanOptions, StrategyPlan};
+use core::ops::{Add, Mul};
+use std::prelude::v1::*;
+use std::vec;
+
+/// The relative cost of each element of a fast Fourier transform, per stage of the transform,
+/// compared to a single multiply and add in a direct convolution.
+const FFT_WEIGHT: f64 = 2.5;
+
+/// The relative cost of applying a pair of mirrored kernel values, which adds the two image values
+/// together before multiplying, compared to a single multiply and add.
+const PAIR_WEIGHT: f64 = 1.5;
+
+/// The number of passes over the image made by every strategy other than [`Strategy::Direct`],
+/// which copy each channel of the image into scratch memory and the result back out of it.
+const CHANNEL_PASSES: f64 = 2.0;
+
+/// A way of computing a convolution.
+///
+/// All strategies give the same result as [`write_convolution`](crate::write_convolution),
+/// including at the edges of the image, up to floating point rounding error. Every strategy other
+/// than [`Strategy::Direct`] works on each of the pixel's [`Channels`] as an `f64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Apply each kernel value to the image in turn. The cost is proportional to the number of
+    /// kernel values.
+    Direct,
+    /// Add each pair of image values which share a kernel value, as found by
+    /// [`kernel::symmetry`], before multiplying them by it. The cost is proportional to the number
+    /// of pairs and unpaired values.
+    Symmetric,
+    /// Split the kernel into a column and a row vector, and apply each as a separate pass. The
+    /// cost is proportional to the sum of the lengths of each vector.
+    Separable,
+    /// Multiply the image and kernel in the frequency domain. The cost is independent of the
+    /// size of the kernel, but grows slightly faster than the size of the image.
+    Fft,
+}
+
+impl Strategy {
+    /// Estimate the fastest strategy for convolving an image of the specified size with the
+    /// provided kernel.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::{kernel, Strategy};
+    /// assert_eq!(Strategy::choose(&kernel::laplacian::cross::<i32>(), 640, 480), Strategy::Direct);
+    /// assert_eq!(Strategy::choose(&kernel::gaussian(41, 7.0), 640, 480), Strategy::Separable);
+    /// ```
+    ///
+    /// # Panics
+    /// If the kernel's `get_value` method does not return `Some` for all row and column values in
+    /// the ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+    pub fn choose<K>(kernel: &impl Matrix<K>, width: usize, height: usize) -> Self
+    where
+        K: Clone + Into<f64>,
+    {
+        let kernel = to_floats(kernel);
+        let separable = kernel::separate(&kernel);
+        Self::choose_with(&kernel, width, height, separable.as_ref())
+    }
+
+    /// Estimate the fastest strategy, given the result of [`kernel::separate`].
+    pub(crate) fn choose_with(
+        kernel: &impl Matrix<f64>,
+        width: usize,
+        height: usize,
+        separable: Option<&(Vec<f64>, Vec<f64>)>,
+    ) -> Self {
+        let pixels = (width * height) as f64;
+
+        let direct = pixels * kernel.get_data().len() as f64;
+        let symmetric = kernel::symmetry(kernel).map(|symmetry| {
+            let (pairs, singles) = symmetric_taps(kernel, symmetry, default_anchor(kernel), width);
+            pixels * (PAIR_WEIGHT * pairs.len() as f64 + singles.len() as f64 + CHANNEL_PASSES)
+        });
+        // The separable strategy makes an extra pass over the image to clear its scratch memory
+        let separable = separable.map(|(column, row)| {
+            pixels * ((column.len() + row.len()) as f64 + 1.0 + CHANNEL_PASSES)
+        });
+        let fft = fft_length(kernel, width, height).map(|len| {
+            let len = len as f64;
+            FFT_WEIGHT * 2.0 * len * len.log2() + len + pixels * CHANNEL_PASSES
+        });
+
+        let mut best = (direct, Self::Direct);
+        for (cost, strategy) in [
+            (symmetric, Self::Symmetric),
+            (separable, Self::Separable),
+            (fft, Self::Fft),
+        ] {
+            if let Some(cost) = cost.filter(|&c| c < best.0) {
+                best = (cost, strategy);
+            }
+        }
+        best.1
+    }
+}
+
+/// Perform a 2D convolution on the specified image with the provided kernel, using the fastest
+/// available [`Strategy`].
+///
+/// This is a convient interface for building a [`StrategyPlan`] with the default
+/// [`PlanOptions`] and executing it once. When convolving many images of the same size, build the
+/// plan once and reuse it instead. Unlike [`convolve2d`](crate::convolve2d), which always applies
+/// the kernel directly, the pixel type must implement [`Channels`] so that the other strategies
+/// can work on each channel as an `f64`.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, convolve2d_auto, kernel, DynamicMatrix, SubPixels};
+/// let data = (0..100 * 100).map(|i| SubPixels([(i % 7) as f64, (i % 3) as f64])).collect();
+/// let image = DynamicMatrix::new(100, 100, data).unwrap();
+/// let kernel = kernel::gaussian(21, 4.0);
+///
+/// let fast: DynamicMatrix<SubPixels<f64, 2>> = convolve2d_auto(&image, &kernel);
+/// let slow: DynamicMatrix<SubPixels<f64, 2>> = convolve2d(&image, &kernel);
+/// for (a, b) in fast.get_data().iter().zip(slow.get_data()) {
+///     assert!((a.0[0] - b.0[0]).abs() < 1e-9 && (a.0[1] - b.0[1]).abs() < 1e-9);
+/// }
+/// # use convolve2d::Matrix;
+/// ```
+///
+/// # Panics
+/// If the kernel's `get_value` method does not return `Some` for all row and column values in the
+/// ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+pub fn convolve2d_auto<T, K, O>(image: &impl Matrix<T>, kernel: &impl Matrix<K>) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O> + Channels + Clone + MaybeSend + MaybeSync,
+    K: Into<f64> + Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Channels + Default + Clone + MaybeSend,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    let mut out = DynamicMatrix::new(width, height, vec![O::default(); width * height]).unwrap();
+    StrategyPlan::with_options(kernel, width, height, PlanOptions::new())
+        .unwrap()
+        .execute(image, &mut out);
+    out
+}
+
+/// Copy the provided kernel into a matrix of `f64` values.
+pub(crate) fn to_floats<K: Clone + Into<f64>>(kernel: &impl Matrix<K>) -> DynamicMatrix<f64> {
+    let (width, height) = (kernel.get_width(), kernel.get_height());
+    let values = (0..height)
+        .flat_map(|row| (0..width).map(move |col| (row, col)))
+        .map(|(row, col)| kernel.get_value(row, col).unwrap().clone().into())
+        .collect();
+    DynamicMatrix::new(width, height, values).unwrap()
+}
+
+/// The mirrored pairs of kernel values which share a multiplication, and the values which are
+/// paired with themselves.
+pub(crate) type SymmetricTaps = (Vec<(isize, isize, f64)>, Vec<(isize, f64)>);
+
+/// Split the kernel into pairs of values which are mirrored by the provided symmetry, each with the
+/// alignments of both values and the value of the first, along with the values which lie on the
+/// mirror itself.
+pub(crate) fn symmetric_taps(
+    kernel: &impl Matrix<f64>,
+    symmetry: Symmetry,
+    (anchor_row, anchor_col): (usize, usize),
+    width: usize,
+) -> SymmetricTaps {
+    let size = (kernel.get_width(), kernel.get_height());
+    let alignment = |(row, col): (usize, usize)| {
+        (anchor_row as isize - row as isize) * width as isize + (anchor_col as isize - col as isize)
+    };
+
+    let (mut pairs, mut singles) = (vec![], vec![]);
+    for row in 0..kernel.get_height() {
+        for col in 0..kernel.get_width() {
+            let value = *kernel.get_value(row, col).unwrap();
+            let pair = symmetry.mirror.pair((row, col), size);
+
+            // Each pair is only applied once, from the value which comes first
+            if (row, col) == pair {
+                singles.push((alignment(pair), value));
+            } else if (row, col) < pair {
+                pairs.push((alignment((row, col)), alignment(pair), value));
+            }
+        }
+    }
+    (pairs, singles)
+}
+
+/// Get the length of the transform needed to convolve an image of the specified size with the
+/// provided kernel, or `None` if the kernel is empty.
+pub(crate) fn fft_length(kernel: &impl Matrix<f64>, width: usize, height: usize) -> Option<usize> {
+    let alignments: Vec<_> = taps(kernel, default_anchor(kernel), width)
+        .map(|(a, _)| a)
+        .collect();
+    let span = alignments.iter().max()? - alignments.iter().min()?;
+    Some((width * height + span as usize).next_power_of_two())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convolve2d_auto, Strategy};
+    use crate::{convolve2d, kernel, write_convolution, DynamicMatrix, Matrix, StrategyPlan};
+    use crate::{Complex, PlanOptions, StaticMatrix, SubPixels};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    fn check(kernel: &impl Matrix<f64>, width: usize, height: usize, strategy: Strategy) {
+        let data = (0..width * height).map(|i| ((i * 37) % 11) as f64 - 4.0);
+        let image = DynamicMatrix::new(width, height, data.collect()).unwrap();
+
+        let mut expected = DynamicMatrix::new(width, height, vec![1.0; width * height]).unwrap();
+        let mut output = DynamicMatrix::new(width, height, vec![1.0; width * height]).unwrap();
+        write_convolution(&image, kernel, &mut expected);
+
+        let options = PlanOptions::new().with_strategy(strategy);
+        let mut plan = StrategyPlan::with_options(kernel, width, height, options).unwrap();
+        assert_eq!(plan.strategy(), strategy);
+        plan.execute(&image, &mut output);
+
+        for (a, b) in output.get_data().iter().zip(expected.get_data()) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+    }
+
+    #[test_case(Strategy::Direct; "direct")]
+    #[test_case(Strategy::Separable; "separable")]
+    #[test_case(Strategy::Fft; "fft")]
+    fn matches_write_convolution(strategy: Strategy) {
+        check(&kernel::gaussian(5, 1.5), 13, 7, strategy);
+        check(&kernel::sobel::y::<f64>(), 4, 9, strategy);
+        check(
+            &StaticMatrix::new(4, 2, [1.0, 2.0, 0.0, -1.0, 2.0, 4.0, 0.0, -2.0]).unwrap(),
+            5,
+            5,
+            strategy,
+        );
+    }
+
+    #[test]
+    fn symmetric() {
+        check(&kernel::gaussian(5, 1.5), 13, 7, Strategy::Symmetric);
+        check(&kernel::sobel::y::<f64>(), 4, 9, Strategy::Symmetric);
+        check(&kernel::sobel::x::<f64>(), 9, 4, Strategy::Symmetric);
+        check(
+            &kernel::laplacian::full::<f64>(),
+            6,
+            11,
+            Strategy::Symmetric,
+        );
+        // Wider than the image, so values wrap onto the neighbouring rows
+        check(
+            &StaticMatrix::new(5, 1, [1.0, -2.0, 0.0, 2.0, -1.0]).unwrap(),
+            3,
+            4,
+            Strategy::Symmetric,
+        );
+
+        let options = PlanOptions::new().with_strategy(Strategy::Symmetric);
+        let asymmetric = StaticMatrix::new(2, 1, [1.0, 2.0]).unwrap();
+        assert!(StrategyPlan::with_options(&asymmetric, 4, 4, options).is_none());
+    }
+
+    #[test]
+    fn fft_non_separable() {
+        check(&kernel::laplacian::full::<f64>(), 6, 11, Strategy::Fft);
+        check(&kernel::box_blur(9), 3, 2, Strategy::Fft);
+    }
+
+    #[test]
+    fn choose() {
+        assert_eq!(
+            Strategy::choose(&kernel::sobel::x::<f64>(), 640, 480),
+            Strategy::Direct
+        );
+        assert_eq!(
+            Strategy::choose(&kernel::gaussian(41, 7.0), 256, 256),
+            Strategy::Separable
+        );
+
+        let data = (0..31 * 31).map(|i| (i % 5) as f64).collect();
+        let dense = DynamicMatrix::new(31, 31, data).unwrap();
+        assert!(kernel::separate(&dense).is_none());
+        assert_eq!(Strategy::choose(&dense, 256, 256), Strategy::Fft);
+
+        // Symmetric about both axes, but not separable
+        let data = (0..25)
+            .map(|i: i32| (i / 5 - 2).pow(2) + (i % 5 - 2).pow(2))
+            .collect();
+        let bowl = DynamicMatrix::new(5, 5, data).unwrap();
+        assert_eq!(Strategy::choose(&bowl, 640, 480), Strategy::Symmetric);
+    }
+
+    #[test]
+    fn auto_matches_convolve2d() {
+        let data = (0..64 * 64)
+            .map(|i| SubPixels([i % 7, i % 5 - 2, i % 11]))
+            .collect();
+        let image = DynamicMatrix::new(64, 64, data).unwrap();
+        let kernel = DynamicMatrix::new(15, 15, (0..225).map(|i| i % 4 - 1).collect()).unwrap();
+
+        assert_eq!(Strategy::choose(&kernel, 64, 64), Strategy::Fft);
+        let auto: DynamicMatrix<SubPixels<i32, 3>> = convolve2d_auto(&image, &kernel);
+        let direct: DynamicMatrix<SubPixels<i32, 3>> = convolve2d(&image, &kernel);
+        assert_eq!(auto, direct);
+
+        let complex =
+            DynamicMatrix::new(3, 2, (0..6).map(|i| Complex::new(i as f64, 1.0)).collect())
+                .unwrap();
+        let identity = StaticMatrix::new(1, 1, [2.0]).unwrap();
+        let doubled: DynamicMatrix<Complex<f64>> = convolve2d_auto(&complex, &identity);
+        assert_eq!(doubled.get_data()[4], Complex::new(8.0, 2.0));
+    }
+}
@@ -0,0 +1,405 @@
+//! Definitions of the [`StreamConvolver`], which convolves images one row at a time, and of
+//! in-place convolution, which is built on the same ring buffer of image rows.
+
+use crate::convolution::taps;
+use crate::kernel::default_anchor;
+use crate::{Matrix, MatrixMut};
+use core::ops::{Add, Mul};
+
+/// A convolution which receives the image one row at a time, and emits each row of the output as
+/// soon as it is complete.
+///
+/// Only the rows of the image which are still needed are kept, in a ring buffer provided by the
+/// caller, so images which are too large to fit in memory (such as the output of a line scan
+/// sensor) can be convolved. No allocations are made, so this works in `no_std` environments.
+///
+/// The output is identical to that of [`write_convolution`](crate::write_convolution) applied to
+/// the whole image with a zeroed output buffer. Since `write_convolution` lets kernels which
+/// extend past the left or right side of the image wrap onto the neighbouring rows, the ring
+/// buffer holds a couple more rows than the height of the kernel. Use [`stream_ring_len`] to find
+/// the required size.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, stream_ring_len, StreamConvolver};
+/// let kernel = kernel::box_blur(3);
+/// let mut ring = vec![0.0; stream_ring_len(&kernel, 4)];
+/// let mut row_buffer = [0.0; 4];
+/// let mut stream = StreamConvolver::new(&kernel, 4, &mut ring, &mut row_buffer).unwrap();
+///
+/// let mut emitted = vec![];
+/// for row in 0..6 {
+///     stream.push_row(&[row as f64; 4], |index, _data: &[f64]| emitted.push(index));
+/// }
+/// // The last rows can't be finished until we know there are no more rows coming
+/// assert_eq!(emitted, vec![0, 1, 2, 3]);
+///
+/// stream.finish(|index, _data| emitted.push(index));
+/// assert_eq!(emitted, vec![0, 1, 2, 3, 4, 5]);
+/// ```
+#[derive(Debug)]
+pub struct StreamConvolver<'a, K, M, T, O> {
+    /// The kernel being applied
+    kernel: &'a M,
+    /// The `(row, col)` position of the kernel that sits on top of each output pixel
+    anchor: (usize, usize),
+    /// The number of columns in the image
+    width: usize,
+    /// The most recent rows of the image
+    ring: Ring<'a, T>,
+    /// The buffer in which each output row is computed
+    row_buffer: &'a mut [O],
+    /// The number of output rows emitted so far
+    emitted: usize,
+    _marker: core::marker::PhantomData<K>,
+}
+
+impl<'a, K, M, T, O> StreamConvolver<'a, K, M, T, O>
+where
+    K: Clone,
+    M: Matrix<K>,
+    T: Mul<K, Output = O> + Clone,
+    O: Add<Output = O> + Default + Clone,
+{
+    /// Create a new streaming convolution of images with the specified width.
+    ///
+    /// `ring` is used to hold rows of the image, and must be at least
+    /// [`stream_ring_len`] elements long. `row_buffer` is used to compute each row of the
+    /// output, and must be `width` elements long. Returns `None` if either buffer is too short.
+    pub fn new(
+        kernel: &'a M,
+        width: usize,
+        ring: &'a mut [T],
+        row_buffer: &'a mut [O],
+    ) -> Option<Self> {
+        let rows = ring_rows(kernel, width);
+        if ring.len() < rows * width || row_buffer.len() < width {
+            return None;
+        }
+
+        Some(Self {
+            kernel,
+            anchor: default_anchor(kernel),
+            width,
+            ring: Ring {
+                data: &mut ring[..rows * width],
+                rows,
+                width,
+                received: 0,
+            },
+            row_buffer: &mut row_buffer[..width],
+            emitted: 0,
+            _marker: core::marker::PhantomData,
+        })
+    }
+
+    /// Get the number of output rows which have been emitted so far.
+    pub fn emitted(&self) -> usize {
+        self.emitted
+    }
+
+    /// Add the next row of the image, passing each output row which can now be completed to
+    /// `sink`, along with its index.
+    ///
+    /// # Panics
+    /// If the length of `row` is not the width of the image.
+    pub fn push_row(&mut self, row: &[T], mut sink: impl FnMut(usize, &[O])) {
+        assert_eq!(
+            row.len(),
+            self.width,
+            "Row length does not match the image width"
+        );
+
+        self.ring.push(row);
+
+        let received = self.ring.received;
+        while self.emitted < received && self.last_row_needed(self.emitted) < received {
+            self.emit(&mut sink);
+        }
+    }
+
+    /// Signal that the image has no more rows, passing each remaining output row to `sink`.
+    pub fn finish(mut self, mut sink: impl FnMut(usize, &[O])) {
+        while self.emitted < self.ring.received {
+            self.emit(&mut sink);
+        }
+    }
+
+    /// Get the index of the last image row that contributes to the specified output row.
+    fn last_row_needed(&self, row: usize) -> usize {
+        last_row_needed(self.kernel, self.anchor, self.width, row)
+    }
+
+    /// Compute the next output row and pass it to `sink`.
+    fn emit(&mut self, sink: &mut impl FnMut(usize, &[O])) {
+        let row = self.emitted;
+        self.row_buffer.iter_mut().for_each(|x| *x = O::default());
+        self.ring
+            .convolve_row(self.kernel, self.anchor, row, self.row_buffer);
+
+        sink(row, self.row_buffer);
+        self.emitted += 1;
+    }
+}
+
+/// Overwrite an image with its own convolution with the provided kernel, using `scratch` to hold
+/// the rows of the image which are still needed.
+///
+/// Unlike [`write_convolution`](crate::write_convolution), no output buffer the size of the image
+/// is needed. Only a few more rows than the height of the kernel are held in `scratch`, so this
+/// is suitable for memory constrained devices. The result is identical to that of
+/// `write_convolution` with a zeroed output buffer.
+///
+/// # Example
+/// ```
+/// use convolve2d::{kernel, stream_ring_len, write_convolution_in_place, StaticMatrix};
+/// let mut image = StaticMatrix::new(3, 3, [
+///     0, 0, 0,
+///     0, 1, 0,
+///     0, 0, 0,
+/// ]).unwrap();
+/// let kernel = StaticMatrix::new(3, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+///
+/// let mut scratch = [0; 15];
+/// assert_eq!(stream_ring_len(&kernel, 3), scratch.len());
+/// write_convolution_in_place(&mut image, &kernel, &mut scratch);
+/// assert_eq!(image, StaticMatrix::new(3, 3, [9, 8, 7, 6, 5, 4, 3, 2, 1]).unwrap());
+/// ```
+///
+/// # Panics
+/// If `scratch` is shorter than [`stream_ring_len`], or if the kernel's `get_value` method does not
+/// return `Some` for all row and column values in the ranges `0..kernel.get_height()` and
+/// `0..kernel.get_width()`.
+pub fn write_convolution_in_place<T, K>(
+    image: &mut impl MatrixMut<T>,
+    kernel: &impl Matrix<K>,
+    scratch: &mut [T],
+) where
+    T: Mul<K, Output = T> + Add<Output = T> + Default + Clone,
+    K: Clone,
+{
+    let (width, height) = (image.get_width(), image.get_height());
+    let anchor = default_anchor(kernel);
+    let rows = ring_rows(kernel, width);
+    assert!(
+        scratch.len() >= rows * width,
+        "Scratch buffer is too short for the kernel"
+    );
+
+    let mut ring = Ring {
+        data: &mut scratch[..rows * width],
+        rows,
+        width,
+        received: 0,
+    };
+
+    // Each row of the image is copied into the ring before it is overwritten, and the ring always
+    // holds every row that the remaining output rows depend on
+    let mut emitted = 0;
+    for row in 0..height {
+        ring.push(&image.get_data()[row * width..(row + 1) * width]);
+
+        let last = if row + 1 == height { usize::MAX } else { row };
+        while emitted <= row && last_row_needed(kernel, anchor, width, emitted) <= last {
+            let out = &mut image.get_data_mut()[emitted * width..(emitted + 1) * width];
+            out.iter_mut().for_each(|x| *x = T::default());
+            ring.convolve_row(kernel, anchor, emitted, out);
+            emitted += 1;
+        }
+    }
+}
+
+/// The most recent rows of an image, stored in a ring buffer.
+#[derive(Debug)]
+struct Ring<'a, T> {
+    /// The rows of the image, with row `n` stored at index `n % rows`
+    data: &'a mut [T],
+    /// The number of rows the ring buffer holds
+    rows: usize,
+    /// The number of columns in the image
+    width: usize,
+    /// The number of image rows received so far
+    received: usize,
+}
+
+impl<T: Clone> Ring<'_, T> {
+    /// Add the next row of the image, replacing the oldest row.
+    fn push(&mut self, row: &[T]) {
+        let slot = (self.received % self.rows) * self.width;
+        self.data[slot..slot + self.width].clone_from_slice(row);
+        self.received += 1;
+    }
+
+    /// Add the convolution of the specified row to `out`. Rows which have not been received yet
+    /// are treated as zero.
+    fn convolve_row<K, O>(
+        &self,
+        kernel: &impl Matrix<K>,
+        anchor: (usize, usize),
+        row: usize,
+        out: &mut [O],
+    ) where
+        T: Mul<K, Output = O>,
+        K: Clone,
+        O: Add<Output = O> + Clone,
+    {
+        let start = (row * self.width) as isize;
+        for (alignment, value) in taps(kernel, anchor, self.width) {
+            for (col, a) in out.iter_mut().enumerate() {
+                let source = start + col as isize - alignment;
+                let Ok(source) = usize::try_from(source) else {
+                    continue;
+                };
+                let source_row = source / self.width;
+                if source_row >= self.received {
+                    continue;
+                }
+                let index = (source_row % self.rows) * self.width + source % self.width;
+                *a = a.clone() + self.data[index].clone() * value.clone();
+            }
+        }
+    }
+}
+
+/// Get the index of the last image row that contributes to the specified output row.
+fn last_row_needed<K>(
+    kernel: &impl Matrix<K>,
+    anchor: (usize, usize),
+    width: usize,
+    row: usize,
+) -> usize {
+    let below = kernel.get_height().saturating_sub(anchor.0 + 1) as isize;
+    let right = kernel.get_width().saturating_sub(anchor.1 + 1) as isize;
+    // The last pixel of the output row may reach past the end of the next row
+    let overflow = (width as isize - 1 + right).div_euclid(width.max(1) as isize);
+    (row as isize + below + overflow).max(0) as usize
+}
+
+/// Get the length of the ring buffer needed by a [`StreamConvolver`] to convolve images of the
+/// specified width with the provided kernel.
+///
+/// # Example
+/// ```
+/// # use convolve2d::{kernel, stream_ring_len};
+/// // A 3x3 kernel needs to hold 5 rows of the image
+/// assert_eq!(stream_ring_len(&kernel::box_blur(3), 640), 5 * 640);
+/// ```
+pub fn stream_ring_len<K>(kernel: &impl Matrix<K>, width: usize) -> usize {
+    ring_rows(kernel, width) * width
+}
+
+/// Get the number of image rows that contribute to each output row.
+fn ring_rows<K>(kernel: &impl Matrix<K>, width: usize) -> usize {
+    let (anchor_row, anchor_col) = default_anchor(kernel);
+    let below = kernel.get_height().saturating_sub(anchor_row + 1) as isize;
+    let right = kernel.get_width().saturating_sub(anchor_col + 1) as isize;
+    let width = width.max(1) as isize;
+
+    // Pixels at the start and end of each row can reach onto the neighbouring rows
+    let first = -(anchor_row as isize) + (-(anchor_col as isize)).div_euclid(width);
+    let last = below + (width - 1 + right).div_euclid(width);
+    (last - first + 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_row_needed, stream_ring_len, write_convolution_in_place, StreamConvolver};
+    use crate::{write_convolution, StaticMatrix};
+    use test_case::test_case;
+
+    #[test_case(StaticMatrix::new(3, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap(); "square")]
+    #[test_case(StaticMatrix::new(1, 1, [3]).unwrap(); "single")]
+    #[test_case(StaticMatrix::new(7, 1, [1, -1, 2, -2, 3, -3, 4]).unwrap(); "wide")]
+    #[test_case(StaticMatrix::new(1, 7, [1, -1, 2, -2, 3, -3, 4]).unwrap(); "tall")]
+    #[test_case(StaticMatrix::new(2, 4, [1, 0, 2, 0, 0, 3, 0, 4]).unwrap(); "even")]
+    fn matches_write_convolution<const N: usize>(kernel: StaticMatrix<i32, N>) {
+        let data: [i32; 35] = core::array::from_fn(|i| (i as i32 * 7) % 13 - 6);
+
+        let mut expected = StaticMatrix::new(5, 7, [0; 35]).unwrap();
+        write_convolution(
+            &StaticMatrix::new(5, 7, data).unwrap(),
+            &kernel,
+            &mut expected,
+        );
+
+        let mut ring = [0; 128];
+        let mut row_buffer = [0; 5];
+        let mut output = [0; 35];
+        let mut stream = StreamConvolver::new(&kernel, 5, &mut ring, &mut row_buffer).unwrap();
+        let mut sink = |row: usize, data: &[i32]| output[row * 5..][..5].copy_from_slice(data);
+
+        for row in data.chunks(5) {
+            stream.push_row(row, &mut sink);
+        }
+        stream.finish(&mut sink);
+
+        assert_eq!(output, expected.into_parts().2);
+    }
+
+    #[test_case(StaticMatrix::new(3, 3, [1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap(); "square")]
+    #[test_case(StaticMatrix::new(1, 1, [3]).unwrap(); "single")]
+    #[test_case(StaticMatrix::new(7, 1, [1, -1, 2, -2, 3, -3, 4]).unwrap(); "wide")]
+    #[test_case(StaticMatrix::new(1, 7, [1, -1, 2, -2, 3, -3, 4]).unwrap(); "tall")]
+    #[test_case(StaticMatrix::new(2, 4, [1, 0, 2, 0, 0, 3, 0, 4]).unwrap(); "even")]
+    fn in_place_matches_write_convolution<const N: usize>(kernel: StaticMatrix<i32, N>) {
+        let data: [i32; 35] = core::array::from_fn(|i| (i as i32 * 5) % 11 - 4);
+
+        let mut expected = StaticMatrix::new(5, 7, [0; 35]).unwrap();
+        write_convolution(
+            &StaticMatrix::new(5, 7, data).unwrap(),
+            &kernel,
+            &mut expected,
+        );
+
+        let mut scratch = [0; 128];
+        let scratch = &mut scratch[..stream_ring_len(&kernel, 5)];
+        let mut image = StaticMatrix::new(5, 7, data).unwrap();
+        write_convolution_in_place(&mut image, &kernel, scratch);
+
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn last_row_needed_empty_kernel() {
+        let kernel = StaticMatrix::new(0, 0, [0; 0]).unwrap();
+        assert_eq!(last_row_needed(&kernel, (0, 0), 5, 3), 3);
+    }
+
+    #[test]
+    fn empty_kernel() {
+        let kernel = StaticMatrix::new(0, 0, [0; 0]).unwrap();
+        assert_eq!(stream_ring_len(&kernel, 4), 4);
+
+        let mut ring = [0; 4];
+        let mut row_buffer = [0; 4];
+        let mut stream = StreamConvolver::new(&kernel, 4, &mut ring, &mut row_buffer).unwrap();
+        let mut emitted = 0;
+        stream.push_row(&[1, 2, 3, 4], |_, data: &[i32]| {
+            assert_eq!(data, &[0; 4]);
+            emitted += 1;
+        });
+        assert_eq!(emitted, 1);
+
+        let mut image = StaticMatrix::new(4, 2, [1; 8]).unwrap();
+        write_convolution_in_place(&mut image, &kernel, &mut [0; 4]);
+        assert_eq!(image.into_parts().2, [0; 8]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn in_place_scratch_too_small() {
+        let kernel = StaticMatrix::new(3, 3, [1; 9]).unwrap();
+        let mut image = StaticMatrix::new(4, 4, [1; 16]).unwrap();
+        write_convolution_in_place(&mut image, &kernel, &mut [0; 19]);
+    }
+
+    #[test]
+    fn ring_too_small() {
+        let kernel = StaticMatrix::new(3, 3, [1; 9]).unwrap();
+        assert_eq!(stream_ring_len(&kernel, 10), 50);
+
+        let mut ring = [0; 49];
+        let mut row_buffer = [0; 10];
+        assert!(StreamConvolver::new(&kernel, 10, &mut ring, &mut row_buffer).is_none());
+    }
+}
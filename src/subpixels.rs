@@ -1,5 +1,5 @@
-use crate::{SaturatingAdd, SaturatingMul};
-use core::ops::{Add, Mul};
+use crate::{Channels, CompensatedAdd, Lattice, SaturatingAdd, SaturatingMul, SaturatingSub};
+use core::ops::{Add, Div, Mul, Sub};
 
 /// A collection of subpixels that should make working with multi-channeled images more convenient.
 ///
@@ -93,6 +93,42 @@ impl<T: Add<Output = T> + Copy, const N: usize> Add for SubPixels<T, N> {
     }
 }
 
+impl<T: Sub<Output = T> + Copy, const N: usize> Sub for SubPixels<T, N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        for (i, x) in rhs.0.into_iter().enumerate() {
+            self.0[i] = self.0[i] - x;
+        }
+        self
+    }
+}
+
+/// Add the same value to each subpixel. This allows a [`Kernel`](crate::Kernel)'s bias to be
+/// applied to multi-channeled images.
+impl<T: Add<Output = T> + Copy, const N: usize> Add<T> for SubPixels<T, N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: T) -> Self::Output {
+        for x in self.0.iter_mut() {
+            *x = *x + rhs;
+        }
+        self
+    }
+}
+
+/// Subtract the same value from each subpixel.
+impl<T: Sub<Output = T> + Copy, const N: usize> Sub<T> for SubPixels<T, N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: T) -> Self::Output {
+        for x in self.0.iter_mut() {
+            *x = *x - rhs;
+        }
+        self
+    }
+}
+
 impl<T: SaturatingAdd<Output = T> + Copy, const N: usize> SaturatingAdd for SubPixels<T, N> {
     type Output = Self;
 
@@ -104,6 +140,92 @@ impl<T: SaturatingAdd<Output = T> + Copy, const N: usize> SaturatingAdd for SubP
     }
 }
 
+/// Add the same value to each subpixel without overflowing. This allows a grayscale structuring
+/// element to be used by [`dilate`](crate::dilate) on multi-channeled images.
+impl<T: SaturatingAdd<Output = T> + Copy, const N: usize> SaturatingAdd<T> for SubPixels<T, N> {
+    type Output = Self;
+
+    fn saturating_add(mut self, rhs: T) -> Self::Output {
+        for x in self.0.iter_mut() {
+            *x = x.saturating_add(rhs);
+        }
+        self
+    }
+}
+
+impl<T: SaturatingSub<Output = T> + Copy, const N: usize> SaturatingSub for SubPixels<T, N> {
+    type Output = Self;
+
+    fn saturating_sub(mut self, rhs: Self) -> Self::Output {
+        for (i, x) in rhs.0.into_iter().enumerate() {
+            self.0[i] = self.0[i].saturating_sub(x);
+        }
+        self
+    }
+}
+
+/// Subtract the same value from each subpixel without overflowing. This allows a grayscale
+/// structuring element to be used by [`erode`](crate::erode) on multi-channeled images.
+impl<T: SaturatingSub<Output = T> + Copy, const N: usize> SaturatingSub<T> for SubPixels<T, N> {
+    type Output = Self;
+
+    fn saturating_sub(mut self, rhs: T) -> Self::Output {
+        for x in self.0.iter_mut() {
+            *x = x.saturating_sub(rhs);
+        }
+        self
+    }
+}
+
+impl<T: CompensatedAdd + Copy, const N: usize> CompensatedAdd for SubPixels<T, N> {
+    fn compensated_add(mut self, rhs: Self, compensation: &mut Self) -> Self {
+        for (i, x) in rhs.0.into_iter().enumerate() {
+            self.0[i] = self.0[i].compensated_add(x, &mut compensation.0[i]);
+        }
+        self
+    }
+}
+
+/// Subpixels are ordered one channel at a time, so the `sup` of two `SubPixels` takes the larger
+/// value of each channel independently.
+impl<T: Lattice + Copy, const N: usize> Lattice for SubPixels<T, N> {
+    fn bottom() -> Self {
+        Self([T::bottom(); N])
+    }
+
+    fn top() -> Self {
+        Self([T::top(); N])
+    }
+
+    fn sup(mut self, rhs: Self) -> Self {
+        for (i, x) in rhs.0.into_iter().enumerate() {
+            self.0[i] = self.0[i].sup(x);
+        }
+        self
+    }
+
+    fn inf(mut self, rhs: Self) -> Self {
+        for (i, x) in rhs.0.into_iter().enumerate() {
+            self.0[i] = self.0[i].inf(x);
+        }
+        self
+    }
+}
+
+impl<T: Channels + Copy, const N: usize> Channels for SubPixels<T, N> {
+    const CHANNELS: usize = N * T::CHANNELS;
+
+    fn channel(&self, index: usize) -> f64 {
+        self.0[index / T::CHANNELS].channel(index % T::CHANNELS)
+    }
+
+    fn from_channels(mut channel: impl FnMut(usize) -> f64) -> Self {
+        Self(core::array::from_fn(|i| {
+            T::from_channels(|j| channel(i * T::CHANNELS + j))
+        }))
+    }
+}
+
 impl<T, C, O, const N: usize> Mul<C> for SubPixels<T, N>
 where
     C: Copy,
@@ -123,6 +245,25 @@ where
     }
 }
 
+impl<T, C, O, const N: usize> Div<C> for SubPixels<T, N>
+where
+    C: Copy,
+    T: Div<C, Output = O> + Copy,
+    O: Default + Copy,
+{
+    type Output = SubPixels<O, N>;
+
+    fn div(self, rhs: C) -> Self::Output {
+        let mut arr = [O::default(); N];
+        self.0
+            .into_iter()
+            .map(|a| a / rhs)
+            .enumerate()
+            .for_each(|(i, v)| arr[i] = v);
+        SubPixels(arr)
+    }
+}
+
 impl<T, C, O, const N: usize> SaturatingMul<C> for SubPixels<T, N>
 where
     C: Copy,
@@ -150,7 +291,7 @@ impl<T: Copy + Default, const N: usize> Default for SubPixels<T, N> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{SaturatingAdd, SaturatingMul};
+    use crate::{CompensatedAdd, Lattice, SaturatingAdd, SaturatingMul};
 
     use super::SubPixels;
 
@@ -174,6 +315,42 @@ mod tests {
         assert_eq!(p1 * 5, SubPixels([5, 10, 15, 20]))
     }
 
+    #[test]
+    fn test_sub() {
+        let p1 = SubPixels([5, 5, 5, 5]);
+        let p2 = SubPixels([1, 2, 3, 4]);
+        assert_eq!(p1 - p2, SubPixels([4, 3, 2, 1]))
+    }
+
+    #[test]
+    fn test_add_scalar() {
+        let p1 = SubPixels([1, 2, 3, 4]);
+        assert_eq!(p1 + 10, SubPixels([11, 12, 13, 14]))
+    }
+
+    #[test]
+    fn test_compensated_add() {
+        let mut compensation = SubPixels([0.0; 2]);
+        let sum = SubPixels([1e16, 1.0]).compensated_add(SubPixels([1.0, 2.0]), &mut compensation);
+        assert_eq!(sum, SubPixels([1e16, 3.0]));
+        assert_eq!(compensation, SubPixels([1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_lattice() {
+        let p1 = SubPixels::<u8, 3>([1, 20, 3]);
+        let p2 = SubPixels::<u8, 3>([10, 2, 3]);
+        assert_eq!(p1.sup(p2), SubPixels([10, 20, 3]));
+        assert_eq!(p1.inf(p2), SubPixels([1, 2, 3]));
+        assert_eq!(SubPixels::<u8, 3>::bottom(), SubPixels([0; 3]));
+    }
+
+    #[test]
+    fn test_div() {
+        let p1 = SubPixels([5, 10, 15, 21]);
+        assert_eq!(p1 / 5, SubPixels([1, 2, 3, 4]))
+    }
+
     #[test]
     fn test_saturating_mul() {
         let p1 = SubPixels::<u8, 4>([2, 8, 32, 128]);
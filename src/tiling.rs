@@ -0,0 +1,341 @@
+//! Tiled convolution of images which are too large to hold in memory at once.
+
+use crate::convolution::taps;
+use crate::kernel::default_anchor;
+use crate::{DynamicMatrix, Matrix, MatrixMut, MaybeSend, MaybeSync};
+use core::ops::{Add, Mul};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::prelude::v1::*;
+use std::vec;
+
+/// A source of image data which can be read one rectangular region at a time.
+///
+/// Implement this trait for images which are stored on disk, or generated on demand, to convolve
+/// them with [`convolve_tiled`] without loading the whole image into memory. Every [`Matrix`]
+/// is also a `TileSource`.
+pub trait TileSource<T> {
+    /// Get the width of the whole image
+    fn width(&self) -> usize;
+
+    /// Get the height of the whole image
+    fn height(&self) -> usize;
+
+    /// Copy the region of the image with its top left corner at `(row, col)` into `buf`, in
+    /// row-major order.
+    ///
+    /// The region is `width` columns wide and `buf.len() / width` rows tall, and always lies
+    /// within the image.
+    fn read_region(&self, row: usize, col: usize, width: usize, buf: &mut [T]);
+}
+
+impl<T: Clone, M: Matrix<T>> TileSource<T> for M {
+    fn width(&self) -> usize {
+        self.get_width()
+    }
+
+    fn height(&self) -> usize {
+        self.get_height()
+    }
+
+    fn read_region(&self, row: usize, col: usize, width: usize, buf: &mut [T]) {
+        let stride = self.get_width();
+        for (r, line) in buf.chunks_mut(width.max(1)).enumerate() {
+            let start = (row + r) * stride + col;
+            line.clone_from_slice(&self.get_data()[start..start + line.len()]);
+        }
+    }
+}
+
+/// The position and size of a tile within the output image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// The index of the top row of the tile
+    pub row: usize,
+    /// The index of the leftmost column of the tile
+    pub col: usize,
+    /// The number of columns in the tile
+    pub width: usize,
+    /// The number of rows in the tile
+    pub height: usize,
+}
+
+/// Convolve a large image one tile at a time, passing each tile of the output to `sink` as it is
+/// completed.
+///
+/// The output is split into tiles of at most `tile_width` by `tile_height` pixels. For each tile,
+/// only the region of the source covered by the tile and a halo the size of the kernel is read,
+/// so the whole image never needs to be held in memory. The tiles in each band of `tile_height`
+/// rows are processed in parallel when the `rayon` feature is enabled, and are then passed to
+/// `sink` one at a time, in row-major order.
+///
+/// Stitching the tiles together gives exactly the same result as
+/// [`convolve2d`](crate::convolve2d) on the whole image, including the handling of the image's
+/// borders. Use [`write_convolution_tiled`] to stitch them into an output matrix.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve_tiled, kernel, DynamicMatrix};
+///
+/// let image = DynamicMatrix::new(100, 80, vec![1.0; 100 * 80]).unwrap();
+/// let mut tiles = vec![];
+/// convolve_tiled(&image, &kernel::box_blur(3), 32, 32, |tile, _data: &DynamicMatrix<f64>| {
+///     tiles.push(tile);
+/// });
+/// assert_eq!(tiles.len(), 4 * 3);
+/// ```
+///
+/// # Panics
+/// If either tile dimension is zero, or if the kernel's `get_value` method does not return `Some`
+/// for all row and column values in the ranges `0..kernel.get_height()` and
+/// `0..kernel.get_width()`.
+pub fn convolve_tiled<T, K, O>(
+    source: &(impl TileSource<T> + MaybeSync),
+    kernel: &(impl Matrix<K> + MaybeSync),
+    tile_width: usize,
+    tile_height: usize,
+    mut sink: impl FnMut(Tile, &DynamicMatrix<O>),
+) where
+    T: Mul<K, Output = O> + Default + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    assert!(tile_width > 0 && tile_height > 0, "Tiles must not be empty");
+    let (width, height) = (source.width(), source.height());
+
+    for row in (0..height).step_by(tile_height) {
+        let band: Vec<_> = (0..width)
+            .step_by(tile_width)
+            .map(|col| Tile {
+                row,
+                col,
+                width: tile_width.min(width - col),
+                height: tile_height.min(height - row),
+            })
+            .collect();
+
+        #[cfg(not(feature = "rayon"))]
+        let tile_iter = band.into_iter();
+        #[cfg(feature = "rayon")]
+        let tile_iter = band.into_par_iter();
+
+        let results: Vec<_> = tile_iter
+            .map(|tile| (tile, convolve_tile(source, kernel, tile)))
+            .collect();
+        for (tile, data) in &results {
+            sink(*tile, data);
+        }
+    }
+}
+
+/// Convolve a large image one tile at a time, writing the result into the specified buffer.
+///
+/// This reads the source in the same way as [`convolve_tiled`], and stitches each tile into
+/// `out` as it is completed. As with [`write_convolution`](crate::write_convolution), the result
+/// of the convolution is added to the values already in `out`.
+///
+/// Requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use convolve2d::{convolve2d, kernel, write_convolution_tiled, DynamicMatrix};
+/// let image = DynamicMatrix::new(100, 80, (0..8000).map(|i| (i % 13) as f64).collect()).unwrap();
+/// let kernel = kernel::box_blur(5);
+///
+/// let mut output = DynamicMatrix::new(100, 80, vec![0.0; 100 * 80]).unwrap();
+/// write_convolution_tiled(&image, &kernel, 32, 32, &mut output);
+///
+/// let expected: DynamicMatrix<f64> = convolve2d(&image, &kernel);
+/// assert_eq!(output, expected);
+/// ```
+///
+/// # Panics
+/// If either tile dimension is zero, if the dimensions of `out` do not match those of the source,
+/// or if the kernel's `get_value` method does not return `Some` for all row and column values in
+/// the ranges `0..kernel.get_height()` and `0..kernel.get_width()`.
+pub fn write_convolution_tiled<T, K, O>(
+    source: &(impl TileSource<T> + MaybeSync),
+    kernel: &(impl Matrix<K> + MaybeSync),
+    tile_width: usize,
+    tile_height: usize,
+    out: &mut impl MatrixMut<O>,
+) where
+    T: Mul<K, Output = O> + Default + Clone + MaybeSend + MaybeSync,
+    K: Clone + MaybeSend + MaybeSync,
+    O: Add<Output = O> + Default + Clone + MaybeSend,
+{
+    assert!(
+        out.get_width() == source.width() && out.get_height() == source.height(),
+        "Output dimensions do not match the source"
+    );
+    let width = out.get_width();
+    let buf = out.get_data_mut();
+
+    convolve_tiled(source, kernel, tile_width, tile_height, |tile, data| {
+        for (r, line) in data.get_data().chunks(tile.width).enumerate() {
+            let start = (tile.row + r) * width + tile.col;
+            for (a, x) in buf[start..start + tile.width].iter_mut().zip(line) {
+                *a = a.clone() + x.clone();
+            }
+        }
+    });
+}
+
+/// Convolve a single tile of the image.
+fn convolve_tile<T, K, O>(
+    source: &impl TileSource<T>,
+    kernel: &impl Matrix<K>,
+    tile: Tile,
+) -> DynamicMatrix<O>
+where
+    T: Mul<K, Output = O> + Default + Clone,
+    K: Clone,
+    O: Add<Output = O> + Default + Clone,
+{
+    let anchor = default_anchor(kernel);
+    let halo_width = tile.width + kernel.get_width() - 1;
+    let halo = read_halo(source, kernel, anchor, tile);
+
+    // The halo has the kernel's anchor sitting on top of the first output pixel, so the kernel
+    // never reaches outside of it
+    let mut out = vec![O::default(); tile.width * tile.height];
+    for (alignment, value) in taps(kernel, anchor, halo_width) {
+        for (i, a) in out.iter_mut().enumerate() {
+            let (row, col) = (i / tile.width + anchor.0, i % tile.width + anchor.1);
+            let index = (row * halo_width + col) as isize - alignment;
+            *a = a.clone() + halo[index as usize].clone() * value.clone();
+        }
+    }
+    DynamicMatrix::new(tile.width, tile.height, out).unwrap()
+}
+
+/// Read the region of the image needed to convolve the provided tile.
+///
+/// Like [`write_convolution`](crate::write_convolution), kernel values which fall off the left or
+/// right side of the image wrap onto the neighbouring row, and values which fall off the top or
+/// bottom are zero.
+fn read_halo<T: Default + Clone, K>(
+    source: &impl TileSource<T>,
+    kernel: &impl Matrix<K>,
+    anchor: (usize, usize),
+    tile: Tile,
+) -> Vec<T> {
+    let (width, height) = (source.width() as isize, source.height() as isize);
+    let halo_width = tile.width + kernel.get_width() - 1;
+    let halo_height = tile.height + kernel.get_height() - 1;
+    let mut halo = vec![T::default(); halo_width * halo_height];
+
+    let top = tile.row as isize - anchor.0 as isize;
+    let left = tile.col as isize - anchor.1 as isize;
+
+    // Split the halo into pieces which come from the same rows of the image, wrapping columns
+    // that are outside of the image onto the neighbouring rows
+    let mut start = 0;
+    while start < halo_width {
+        let shift = (left + start as isize).div_euclid(width);
+        let col = (left + start as isize).rem_euclid(width) as usize;
+        let piece_width = (width as usize - col).min(halo_width - start);
+
+        let first_row = (top + shift).clamp(0, height);
+        let last_row = (top + shift + halo_height as isize).clamp(0, height);
+        if first_row < last_row {
+            let rows = (last_row - first_row) as usize;
+            let mut piece = vec![T::default(); piece_width * rows];
+            source.read_region(first_row as usize, col, piece_width, &mut piece);
+
+            let halo_row = (first_row - top - shift) as usize;
+            for (r, line) in piece.chunks(piece_width).enumerate() {
+                let offset = (halo_row + r) * halo_width + start;
+                halo[offset..offset + piece_width].clone_from_slice(line);
+            }
+        }
+        start += piece_width;
+    }
+    halo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convolve_tiled, write_convolution_tiled};
+    use crate::{convolve2d, DynamicMatrix, Matrix, StaticMatrix};
+    use std::prelude::v1::*;
+    use std::vec;
+    use test_case::test_case;
+
+    fn stitch<const N: usize>(
+        image: &DynamicMatrix<i64>,
+        kernel: &StaticMatrix<i64, N>,
+        tile_width: usize,
+        tile_height: usize,
+    ) -> DynamicMatrix<i64> {
+        let (width, height) = (image.get_width(), image.get_height());
+        let mut out = DynamicMatrix::new(width, height, vec![0; width * height]).unwrap();
+        write_convolution_tiled(image, kernel, tile_width, tile_height, &mut out);
+        out
+    }
+
+    #[test_case(7, 5; "uneven")]
+    #[test_case(1, 1; "single_pixel")]
+    #[test_case(64, 64; "whole_image")]
+    #[test_case(3, 40; "tall")]
+    fn matches_convolve2d(tile_width: usize, tile_height: usize) {
+        let data = (0..23 * 17).map(|i| (i * 31) % 19 - 9).collect();
+        let image = DynamicMatrix::new(23, 17, data).unwrap();
+        let kernel = StaticMatrix::new(4, 3, [1, -2, 3, 0, 5, -6, 7, 8, 0, 9, 1, -1]).unwrap();
+
+        let expected: DynamicMatrix<i64> = convolve2d(&image, &kernel);
+        assert_eq!(stitch(&image, &kernel, tile_width, tile_height), expected);
+    }
+
+    #[test]
+    fn kernel_wider_than_image() {
+        let image = DynamicMatrix::new(3, 4, (0..12).collect()).unwrap();
+        let kernel = StaticMatrix::new(7, 1, [1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+        let expected: DynamicMatrix<i64> = convolve2d(&image, &kernel);
+        assert_eq!(stitch(&image, &kernel, 2, 2), expected);
+    }
+
+    #[test]
+    fn tiles_in_row_major_order() {
+        let image = DynamicMatrix::new(5, 5, vec![1; 25]).unwrap();
+        let kernel = StaticMatrix::new(1, 1, [2]).unwrap();
+
+        let mut tiles = vec![];
+        convolve_tiled(&image, &kernel, 2, 3, |tile, data: &DynamicMatrix<i32>| {
+            assert!(data.get_data().iter().all(|&x| x == 2));
+            tiles.push((tile.row, tile.col, tile.width, tile.height));
+        });
+        assert_eq!(
+            tiles,
+            [
+                (0, 0, 2, 3),
+                (0, 2, 2, 3),
+                (0, 4, 1, 3),
+                (3, 0, 2, 2),
+                (3, 2, 2, 2),
+                (3, 4, 1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn adds_to_output() {
+        let image = DynamicMatrix::new(3, 2, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let kernel = StaticMatrix::new(1, 1, [10]).unwrap();
+        let mut out = DynamicMatrix::new(3, 2, vec![1; 6]).unwrap();
+        write_convolution_tiled(&image, &kernel, 2, 1, &mut out);
+        assert_eq!(out.get_data(), [11, 21, 31, 41, 51, 61]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_output_size() {
+        let image = DynamicMatrix::new(3, 2, vec![1; 6]).unwrap();
+        let kernel = StaticMatrix::new(1, 1, [1]).unwrap();
+        let mut out = DynamicMatrix::new(2, 3, vec![0; 6]).unwrap();
+        write_convolution_tiled(&image, &kernel, 2, 2, &mut out);
+    }
+}
@@ -0,0 +1,229 @@
+//! Definitions for the [`Volume`] trait, and the concrete implementations provided by the library.
+
+#[cfg(feature = "std")]
+use std::prelude::v1::*;
+
+/// An easily implementable interface for three dimensional types that can be used in a
+/// volumetric convolution.
+///
+/// `Volume` is the three dimensional counterpart to [`Matrix`](crate::Matrix), adding a depth to
+/// the width and height. This library comes with two concrete implementations of `Volume`:
+/// [`StaticVolume`], which uses a statically sized array, and [`DynamicVolume`], which uses a
+/// `Vec` under the hood.
+///
+/// # Extensibility
+/// To be able to feed your own types into [`convolve3d`](crate::convolve3d) and
+/// [`write_convolution3d`](crate::write_convolution3d), simply define an implementation of
+/// `Volume` for your types.
+///
+/// Note that it is expected that the slice returned from `get_data` has length
+/// `width * height * depth`, and is stored one slice at a time, with each slice stored in
+/// row-major order. If this invariant is violated, you **are not** going to violate memory safety,
+/// but attempting to run a convolution will either result in a panic, or having garbage returned
+/// as output.
+pub trait Volume<T> {
+    /// Get the width of the volume
+    fn get_width(&self) -> usize;
+
+    /// Get the height of the volume
+    fn get_height(&self) -> usize;
+
+    /// Get the depth of the volume
+    fn get_depth(&self) -> usize;
+
+    /// Retrieve the data stored in this volume.
+    ///
+    /// It is expected that the length of the returned slice will be
+    /// `get_width() * get_height() * get_depth()`.
+    fn get_data(&self) -> &[T];
+
+    /// Get the value stored at the given slice, row, and column of the volume
+    fn get_value(&self, slice: usize, row: usize, col: usize) -> Option<&T> {
+        let index = (slice * self.get_height() + row) * self.get_width() + col;
+        self.get_data().get(index)
+    }
+}
+
+/// A subtype of [`Volume`] allowing mutable access to the underlying data.
+///
+/// # Extensibility
+/// Implement this trait (in addition to `Volume`) if you want to use one of your own types as the
+/// output buffer for [`write_convolution3d`](crate::write_convolution3d).
+pub trait VolumeMut<T>: Volume<T> {
+    /// Get a mutable slice to the underlying volume data
+    fn get_data_mut(&mut self) -> &mut [T];
+}
+
+/// A [`Volume`] with a size known at compile time.
+///
+/// Like [`StaticMatrix`](crate::StaticMatrix), this type is mostly useful for volumes that are
+/// known at compile time, such as the kernels in [`kernel::sobel_3d`](crate::kernel::sobel_3d).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVolume<T, const N: usize> {
+    /// The number of columns in the volume
+    width: usize,
+    /// The number of rows in the volume
+    height: usize,
+    /// The number of slices in the volume
+    depth: usize,
+    /// The set of all values in this volume
+    data: [T; N],
+}
+
+impl<T, const N: usize> StaticVolume<T, N> {
+    /// Create a new `StaticVolume` with the specified dimensions.
+    ///
+    /// Returns `None` if the length of the provided data is not `width * height * depth`
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::StaticVolume;
+    /// assert!(StaticVolume::new(2, 1, 2, [1, 2, 3, 4]).is_some());
+    /// assert!(StaticVolume::new(2, 2, 2, [1, 2, 3, 4]).is_none());
+    /// ```
+    pub fn new(width: usize, height: usize, depth: usize, data: [T; N]) -> Option<Self> {
+        if width * height * depth == data.len() {
+            Some(Self {
+                width,
+                height,
+                depth,
+                data,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Consume `self`, and return the width, height, depth, and volume data (in that order).
+    pub fn into_parts(self) -> (usize, usize, usize, [T; N]) {
+        (self.width, self.height, self.depth, self.data)
+    }
+}
+
+impl<T, const N: usize> Volume<T> for StaticVolume<T, N> {
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    fn get_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    fn get_data(&self) -> &[T] {
+        &self.data
+    }
+}
+
+impl<T, const N: usize> VolumeMut<T> for StaticVolume<T, N> {
+    fn get_data_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+/// A concrete implementation of [`Volume`] for which the size is not known at compile time.
+///
+/// Requires the `std` feature to be enabled. This is the preferred type for storing large volumes,
+/// such as CT scans.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicVolume<T> {
+    /// The number of columns in the volume
+    width: usize,
+    /// The number of rows in the volume
+    height: usize,
+    /// The number of slices in the volume
+    depth: usize,
+    /// The set of all values in this volume
+    data: Vec<T>,
+}
+
+#[cfg(feature = "std")]
+impl<T> DynamicVolume<T> {
+    /// Create a new `DynamicVolume` with the specified data
+    ///
+    /// Returns `None` if the length of the provided data is not `width * height * depth`.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::DynamicVolume;
+    /// assert!(DynamicVolume::new(2, 3, 4, vec![0f64; 24]).is_some());
+    /// assert!(DynamicVolume::new(2, 3, 4, vec![0f64; 23]).is_none());
+    /// ```
+    pub fn new(width: usize, height: usize, depth: usize, data: Vec<T>) -> Option<Self> {
+        if width * height * depth == data.len() {
+            Some(Self {
+                width,
+                height,
+                depth,
+                data,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Perform a map operation on this volume.
+    ///
+    /// # Example
+    /// ```
+    /// # use convolve2d::DynamicVolume;
+    /// let vol: DynamicVolume<u16> = DynamicVolume::new(1, 2, 2, vec![1, 2, 3, 4]).unwrap();
+    /// assert_eq!(
+    ///     vol.map(|x| x as f64),
+    ///     DynamicVolume::new(1, 2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap()
+    /// );
+    /// ```
+    pub fn map<F: Fn(T) -> O, O>(self, operation: F) -> DynamicVolume<O> {
+        let arr = self.data.into_iter().map(operation).collect();
+        DynamicVolume::new(self.width, self.height, self.depth, arr).unwrap()
+    }
+
+    /// Consume `self`, and return the width, height, depth, and volume data (in that order).
+    pub fn into_parts(self) -> (usize, usize, usize, Vec<T>) {
+        (self.width, self.height, self.depth, self.data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Volume<T> for DynamicVolume<T> {
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    fn get_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    fn get_data(&self) -> &[T] {
+        self.data.as_slice()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> VolumeMut<T> for DynamicVolume<T> {
+    fn get_data_mut(&mut self) -> &mut [T] {
+        self.data.as_mut_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StaticVolume, Volume};
+
+    #[test]
+    fn get_value() {
+        let vol = StaticVolume::new(3, 2, 2, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]).unwrap();
+        assert_eq!(vol.get_value(0, 0, 0), Some(&0));
+        assert_eq!(vol.get_value(0, 1, 2), Some(&5));
+        assert_eq!(vol.get_value(1, 0, 1), Some(&7));
+        assert_eq!(vol.get_value(1, 1, 2), Some(&11));
+    }
+}